@@ -0,0 +1,233 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::board::{Action, ActionKind, Board, CellContent, GameState};
+use crate::boardconfig::GameMode;
+use crate::solver::{Knowledge, Solver, Technique};
+use crate::CURRENT_BOARD;
+
+/// How a click was made
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum ClickKind {
+    /// The first click, which is always safe
+    First,
+    /// Every cell the click poked was proven safe. Cells that an opening
+    /// uncovered after that don't matter
+    Logical,
+    /// The click poked a cell that wasn't proven safe
+    Guess
+}
+
+/// The parts of a game, by how many of the safe cells were uncovered
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum Phase {
+    /// Less than a third
+    Opening,
+    /// Less than two thirds
+    Midgame,
+    Endgame
+}
+
+/// What the analysis found about one click
+#[derive(Serialize)]
+pub struct ClickReport {
+    x: usize,
+    y: usize,
+    /// Time since the board was generated
+    elapsed_ms: u64,
+    kind: ClickKind,
+    /// The chance that the click was safe, given what the player could see
+    survival_chance: f64,
+    /// Cells that were proven safe when the player guessed instead
+    missed_safe_cells: Vec<(usize, usize)>,
+    /// True if this click hit a mine
+    fatal: bool
+}
+
+/// Time spent in a phase of the game
+#[derive(Serialize)]
+pub struct PhaseTime {
+    phase: Phase,
+    time_ms: u64,
+    /// Number of pokes and marks in the phase
+    actions: usize
+}
+
+#[derive(Serialize)]
+pub struct GameAnalysis {
+    game_id: u32,
+    won: bool,
+    clicks: Vec<ClickReport>,
+    logical_clicks: usize,
+    guesses: usize,
+    /// Number of guesses made while a safe cell was available
+    missed_opportunities: usize,
+    /// The chance the click that lost the game had to be safe
+    fatal_click_survival: Option<f64>,
+    phases: Vec<PhaseTime>
+}
+
+#[tauri::command]
+/// Analyses the finished game on the current board from its action log.
+/// If `game_id` is given and belongs to another game, nothing is analysed
+pub fn analyze_game(game_id: Option<u32>) -> Result<GameAnalysis, String> {
+    let board = CURRENT_BOARD.lock().map_err(|_| String::from("Couldn't lock the current board"))?;
+    if game_id.is_some_and(|id| id != board.get_game_id()) {
+        return Err(format!("Game {} is no longer the current game", game_id.unwrap_or_default()));
+    }
+    match board.get_game_state() {
+        GameState::GameOver | GameState::GameWon => {},
+        _ => return Err(String::from("The game isn't over yet"))
+    }
+    if board.get_mode() != GameMode::Classic {
+        return Err(String::from("Only classic games can be analysed"));
+    }
+    if !board.has_standard_mines() || board.is_liar() || board.moves_mines() {
+        return Err(String::from("Games with variant rules can't be analysed"));
+    }
+    Ok(analyze(&board))
+}
+
+/// The cells `action` poked itself, before any opening spread from them:
+/// the clicked cell, or for a chord on an uncovered number, the hidden
+/// cells around it. Every one of those that wasn't flagged was uncovered
+fn poked_cells(board: &Board, solver: &Solver, action: &Action) -> Vec<(usize, usize)> {
+    if solver.get(action.x, action.y) == Knowledge::Unknown {
+        return vec![(action.x, action.y)];
+    }
+    let around = board.neighbours(action.x, action.y);
+    action.uncovered.iter().filter(|cell| around.contains(cell)).copied().collect()
+}
+
+/// Replays the action log of `board`, judging every click by what the
+/// player could see right before it
+fn analyze(board: &Board) -> GameAnalysis {
+    let safe_cell_count = board.get_width() * board.get_height() - board.get_mine_total();
+    let mut solver = Solver::new(board);
+    let mut uncovered = 0;
+    let mut clicks = Vec::new();
+    let mut phases = vec![
+        PhaseTime { phase: Phase::Opening, time_ms: 0, actions: 0 },
+        PhaseTime { phase: Phase::Midgame, time_ms: 0, actions: 0 },
+        PhaseTime { phase: Phase::Endgame, time_ms: 0, actions: 0 }
+    ];
+    let mut last_elapsed = Duration::ZERO;
+
+    for action in board.get_actions() {
+        let phase = &mut phases[(uncovered * 3 / safe_cell_count.max(1)).min(2)];
+        phase.time_ms += action.elapsed.saturating_sub(last_elapsed).as_millis() as u64;
+        phase.actions += 1;
+        last_elapsed = action.elapsed;
+
+        if action.kind == ActionKind::Mark { continue; }
+
+        let fatal = action.uncovered.iter().any(|(x, y)| board.is_mine(*x, *y));
+        let report = if clicks.is_empty() {
+            ClickReport { x: action.x, y: action.y, elapsed_ms: action.elapsed.as_millis() as u64,
+                kind: ClickKind::First, survival_chance: 1.0, missed_safe_cells: Vec::new(), fatal }
+        } else {
+            let poked = poked_cells(board, &solver, action);
+            let forced = solver.forced_cells(Technique::Enumeration);
+            let logical = poked.iter().all(|cell| forced.safe.contains(cell));
+            // The cells of a chord are treated as independent of each other
+            let probabilities = solver.mine_probabilities();
+            let survival_chance = poked.iter()
+                .filter_map(|(x, y)| probabilities.iter().find(|(px, py, _)| px == x && py == y))
+                .map(|(_, _, probability)| 1.0 - probability)
+                .product();
+            ClickReport {
+                x: action.x,
+                y: action.y,
+                elapsed_ms: action.elapsed.as_millis() as u64,
+                kind: if logical { ClickKind::Logical } else { ClickKind::Guess },
+                survival_chance: if logical { 1.0 } else { survival_chance },
+                missed_safe_cells: if logical { Vec::new() } else { forced.safe },
+                fatal
+            }
+        };
+        clicks.push(report);
+
+        for (x, y) in &action.uncovered {
+            match board.get_content(*x, *y) {
+                Ok(CellContent::Mines(_)) | Ok(CellContent::AntiMine) => solver.mark_mine(*x, *y),
+                Ok(CellContent::Number(value)) => {
+                    match board.get_clue(*x, *y) {
+                        Some(clue) => solver.reveal_clue(*x, *y, clue),
+                        None => solver.reveal(*x, *y, value as u8)
+                    }
+                    uncovered += 1;
+                },
+                Err(_) => {}
+            }
+        }
+    }
+
+    GameAnalysis {
+        game_id: board.get_game_id(),
+        won: board.get_game_state() == GameState::GameWon,
+        logical_clicks: clicks.iter().filter(|click| click.kind == ClickKind::Logical).count(),
+        guesses: clicks.iter().filter(|click| click.kind == ClickKind::Guess).count(),
+        missed_opportunities: clicks.iter().filter(|click| !click.missed_safe_cells.is_empty()).count(),
+        fatal_click_survival: clicks.iter().find(|click| click.fatal).map(|click| click.survival_chance),
+        clicks,
+        phases
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::boardconfig::BoardConfig;
+    use crate::boardgenerator;
+    use crate::boardlogic;
+    use crate::bot;
+    use crate::hint::{Hint, HintKind};
+    use crate::settings::GenerationMode;
+
+    /// Lets the bot play a board from `seed`, and returns the board with the
+    /// hints of every poke it made after the first one
+    fn bot_game(seed: u64) -> (Board, Vec<Hint>) {
+        let config = BoardConfig::new(16, 16, 40, 8, 8, GenerationMode::SafeArea).unwrap();
+        let mut board = boardgenerator::create_board(&config, &mut ChaCha8Rng::seed_from_u64(seed));
+        board.exclude_from_statistics();
+        boardlogic::poke_board(8, 8, &mut board);
+        let mut hints = Vec::new();
+        while let Ok((hint, _)) = bot::step(&mut board) {
+            if hint.kind != HintKind::Mine {
+                hints.push(hint);
+            }
+        }
+        (board, hints)
+    }
+
+    #[test]
+    fn clicks_are_judged_by_the_cell_that_was_poked() {
+        let mut flood_filled = 0;
+        for seed in 0..10 {
+            let (board, hints) = bot_game(seed);
+            let analysis = analyze(&board);
+            let pokes: Vec<&Action> = board.get_actions().iter().filter(|action| action.kind == ActionKind::Poke).collect();
+            assert_eq!(analysis.clicks.len(), hints.len() + 1);
+
+            for ((click, hint), action) in analysis.clicks[1..].iter().zip(&hints).zip(&pokes[1..]) {
+                assert_eq!((click.x, click.y), (hint.x, hint.y));
+                if hint.kind == HintKind::Safe {
+                    // A proven safe cell is a logical click, however far its opening reaches
+                    assert_eq!(click.kind, ClickKind::Logical);
+                    assert_eq!(click.survival_chance, 1.0);
+                    if action.uncovered.len() > 1 {
+                        flood_filled += 1;
+                    }
+                } else {
+                    assert_eq!(click.kind, ClickKind::Guess);
+                    assert!((click.survival_chance - (1.0 - hint.mine_chance)).abs() < 1e-9);
+                }
+            }
+        }
+        assert!(flood_filled > 0);
+    }
+}
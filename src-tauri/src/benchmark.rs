@@ -0,0 +1,94 @@
+use std::time::{Duration, Instant};
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use crate::board::{Board, CellState, GameState};
+use crate::boardconfig::BoardConfig;
+use crate::boardgenerator;
+use crate::boardlogic;
+use crate::settings::GenerationMode;
+
+/// Seed of every board that is benchmarked, so runs can be compared
+const SEED: u64 = 45;
+
+/// Number of times each benchmark is run
+const ROUNDS: u32 = 20;
+
+/// Times generation, flood fill and win checking on large boards, and
+/// prints the results. Run with `--benchmark`, from a debug build on Windows
+pub fn run() {
+    println!("Benchmarking {} rounds each", ROUNDS);
+    for (width, height, mines) in [(100, 100, 2000), (100, 100, 500)] {
+        let Ok(config) = BoardConfig::new(width, height, mines, width / 2, height / 2, GenerationMode::SafeArea) else {
+            println!("ERROR (bm.run): Invalid benchmark board {}x{} with {} mines", width, height, mines);
+            continue;
+        };
+        let name = format!("{}x{}, {} mines", width, height, mines);
+        benchmark_generation(&name, &config);
+        benchmark_flood_fill(&name, &config);
+        benchmark_win_check(&name, &config);
+    }
+}
+
+fn benchmark_generation(name: &str, config: &BoardConfig) {
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        boardgenerator::create_board(config, &mut rng);
+    }
+    report("generation", name, start.elapsed());
+}
+
+/// Times the first poke, which opens the area around the middle of the board
+fn benchmark_flood_fill(name: &str, config: &BoardConfig) {
+    let mut elapsed = Duration::ZERO;
+    let mut opened = 0;
+    for _ in 0..ROUNDS {
+        let mut board = boardgenerator::create_board(config, &mut StdRng::seed_from_u64(SEED));
+        board.exclude_from_statistics();
+        let start = Instant::now();
+        let change = boardlogic::poke_board(config.first_x() as usize, config.first_y() as usize, &mut board);
+        elapsed += start.elapsed();
+        opened = change.get_changes().len();
+    }
+    report(&format!("flood fill ({} cells)", opened), name, elapsed);
+}
+
+/// Times poking every cell without a mine one by one, which checks after
+/// every poke whether the game is won
+fn benchmark_win_check(name: &str, config: &BoardConfig) {
+    let mut elapsed = Duration::ZERO;
+    let mut won = true;
+    for _ in 0..ROUNDS {
+        let mut board = boardgenerator::create_board(config, &mut StdRng::seed_from_u64(SEED));
+        board.exclude_from_statistics();
+        let safe_cells = safe_cells(&board);
+        let start = Instant::now();
+        for (x, y) in safe_cells {
+            boardlogic::poke_board(x, y, &mut board);
+        }
+        elapsed += start.elapsed();
+        won &= board.get_game_state() == GameState::GameWon;
+    }
+    if !won {
+        println!("ERROR (bm.bwc): A board wasn't won after poking every cell without a mine");
+    }
+    report("poke every cell and check for a win", name, elapsed);
+}
+
+fn safe_cells(board: &Board) -> Vec<(usize, usize)> {
+    let mut cells = Vec::new();
+    for y in 0..board.get_height() {
+        for x in 0..board.get_width() {
+            if !board.is_mine(x, y) && board.get_state(x, y) == Ok(CellState::Hidden) {
+                cells.push((x, y));
+            }
+        }
+    }
+    cells
+}
+
+fn report(benchmark: &str, board: &str, elapsed: Duration) {
+    println!("{:<40} {:<24} {:>10.3} ms per round", benchmark, board, elapsed.as_secs_f64() * 1000.0 / ROUNDS as f64);
+}
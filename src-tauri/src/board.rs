@@ -1,16 +1,35 @@
-use serde::Serialize;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
 
-#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+use serde::{Serialize, Deserialize};
+
+use crate::boardconfig::{BoardConfig, GameMode, Topology};
+
+/// Most neighbours a cell can have, which is on a board with several layers
+pub const MAX_NEIGHBOURS: usize = 26;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
 pub enum CellState {
     Hidden,
     Flagged,
+    /// Marked as an anti-mine, on boards that have them
+    AntiFlagged,
     Questioned,
     ShowMineExploded,
     ShowValue,
     ShowInvalidMine
 }
 
-#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+/// Source of the IDs that tell games apart, so actions meant for an
+/// older game are never applied to a newer one
+static NEXT_GAME_ID: AtomicU32 = AtomicU32::new(1);
+
+/// Takes the ID of a new game. Games that aren't a `Board` take theirs here too
+pub fn next_game_id() -> u32 {
+    NEXT_GAME_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
 pub enum GameState {
     BeforeGame,
     Playing,
@@ -18,58 +37,274 @@ pub enum GameState {
     GameWon
 }
 
-// The value here is the number of surrounding mines,
-// but value '9' IS the mine
-#[derive(Serialize, Clone, Copy)]
+/// What a cell holds
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum CellContent {
+    /// No mine. The number is the total of the mines in the cells around
+    /// it, minus the anti-mines around it, so it can be negative
+    Number(i8),
+    /// At least one mine, and more on boards with multi-mine cells
+    Mines(u8),
+    /// An anti-mine, which takes 1 off the numbers around it. Poking it
+    /// loses the game just like a mine
+    AntiMine
+}
+
+impl CellContent {
+    pub fn is_mine(&self) -> bool {
+        matches!(self, CellContent::Mines(_))
+    }
+
+    pub fn is_anti_mine(&self) -> bool {
+        *self == CellContent::AntiMine
+    }
+
+    /// True if the cell holds neither mines nor an anti-mine
+    pub fn is_safe(&self) -> bool {
+        matches!(self, CellContent::Number(_))
+    }
+
+    /// Number of mines in the cell itself
+    pub fn mine_count(&self) -> u8 {
+        match self {
+            CellContent::Mines(count) => *count,
+            CellContent::Number(_) | CellContent::AntiMine => 0
+        }
+    }
+
+    /// What the cell adds to the numbers around it
+    pub fn weight(&self) -> i8 {
+        match self {
+            CellContent::Mines(count) => *count as i8,
+            CellContent::Number(_) => 0,
+            CellContent::AntiMine => -1
+        }
+    }
+}
+
+/// A number shown without its exact value on a fog-of-war board
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum Clue {
+    /// The number is from the first to the second value
+    Range(u8, u8),
+    Even,
+    Odd
+}
+
+impl Clue {
+    /// True if the clue can stand for `value`
+    pub fn fits(&self, value: u8) -> bool {
+        match self {
+            Clue::Range(low, high) => (*low..=*high).contains(&value),
+            Clue::Even => value.is_multiple_of(2),
+            Clue::Odd => !value.is_multiple_of(2)
+        }
+    }
+}
+
+impl Default for CellContent {
+    fn default() -> CellContent {
+        CellContent::Number(0)
+    }
+}
+
+/// A cell as it is stored. Boards keep all of them in one `Vec`, so cells
+/// are kept small enough that a row of a large board fits in a few cache lines
+#[derive(Serialize, Deserialize, Clone, Copy)]
 pub struct CellDetails {
     pub state: CellState,
-    pub value: u8
+    pub content: CellContent,
+    /// Number of mines the player flagged in the cell, 0 if it isn't flagged
+    pub flags: u8,
+    /// How much the number shown differs from the real one: -1 or 1 on a
+    /// liar board, otherwise 0. Never sent, as it would give the lie away
+    #[serde(skip)]
+    pub lie: i8,
+    /// What is shown instead of the number on a fog-of-war board, if the
+    /// number is fuzzy
+    pub clue: Option<Clue>
+}
+
+const _: () = assert!(std::mem::size_of::<CellDetails>() <= 8);
+
+/// Scores and turns of a two-player `GameMode::Flags` match
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FlagsMatch {
+    /// Number of mines claimed by each player
+    scores: [usize; 2],
+    /// The player whose turn it is, 0 or 1
+    current_player: usize,
+    /// The player that claimed more than half of the mines. If the match
+    /// is over without a winner, it's a draw
+    winner: Option<usize>
+}
+
+impl FlagsMatch {
+    pub const fn new() -> FlagsMatch {
+        FlagsMatch {
+            scores: [0, 0],
+            current_player: 0,
+            winner: None
+        }
+    }
+
+    pub fn get_current_player(&self) -> usize {
+        self.current_player
+    }
+
+    /// Passes the turn to the other player
+    pub fn next_turn(&mut self) {
+        self.current_player = 1 - self.current_player;
+    }
+
+    /// Gives the current player a point for a claimed mine. Returns true if
+    /// they now have more than half of `total_mines`, which wins the match
+    pub fn claim_mine(&mut self, total_mines: usize) -> bool {
+        self.scores[self.current_player] += 1;
+        if self.scores[self.current_player] * 2 > total_mines {
+            self.winner = Some(self.current_player);
+        }
+        self.winner.is_some()
+    }
+}
+
+/// What the player did
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum ActionKind {
+    Poke,
+    Mark
+}
+
+/// An entry in the action log of a game
+#[derive(Clone)]
+pub struct Action {
+    pub kind: ActionKind,
+    pub x: usize,
+    pub y: usize,
+    /// Time since the board was generated
+    pub elapsed: Duration,
+    /// The cells this action uncovered, including an exploded mine
+    pub uncovered: Vec<(usize, usize)>
 }
 
 pub struct Board {
+    game_id: u32,
     width: usize,
+    /// Number of rows of all layers together
     height: usize,
+    /// Number of layers, which are stored below each other
+    depth: usize,
     hidden_cell_count: usize,
     flag_count: usize,
+    /// Total of the mines the flags stand for, which is more than
+    /// `flag_count` if cells are flagged with several mines
+    flagged_mines: usize,
+    /// Total of the mines in all cells
     mines: usize,
+    /// Number of cells with at least one mine
+    mine_cells: usize,
+    max_cell_mines: u8,
+    anti_mines: usize,
+    /// Number of cells with an anti-mine, which is `anti_mines` once the
+    /// board is generated
+    anti_mine_cells: usize,
+    anti_flag_count: usize,
+    liar: bool,
+    /// Number of pokes after which mines move, 0 if they never do
+    move_interval: u32,
+    mines_per_move: u32,
+    pokes_until_move: u32,
+    topology: Topology,
+    mode: GameMode,
+    /// Only present for `GameMode::Flags`
+    flags_match: Option<FlagsMatch>,
     state: GameState,
-    cells: Vec<Vec<CellDetails>>
+    /// Every cell, row by row, so `(x, y)` is at `y * width + x`
+    cells: Vec<CellDetails>,
+    /// Name of the preset this game counts towards in the statistics
+    preset: String,
+    /// When the board was generated, which is right before the first poke
+    start_time: Option<Instant>,
+    /// The date of the daily challenge, if this board is one
+    daily_date: Option<String>,
+    /// Whether the game counts towards the player's statistics. It doesn't
+    /// once the bot has made a move, or if it is a replayed daily challenge
+    counts_in_statistics: bool,
+    /// Every poke and mark that changed the board, in order
+    actions: Vec<Action>
 }
 
 impl Board {
     pub const fn empty() -> Board {
         Board {
+            game_id: 0,
             width: 0,
             height: 0,
+            depth: 1,
             hidden_cell_count: 0,
             mines: 0,
             flag_count: 0,
+            flagged_mines: 0,
+            mine_cells: 0,
+            max_cell_mines: 1,
+            anti_mines: 0,
+            anti_mine_cells: 0,
+            anti_flag_count: 0,
+            liar: false,
+            move_interval: 0,
+            mines_per_move: 0,
+            pokes_until_move: 0,
+            topology: Topology::Rectangle,
+            mode: GameMode::Classic,
+            flags_match: None,
             state: GameState::BeforeGame,
-            cells: Vec::new()
+            cells: Vec::new(),
+            preset: String::new(),
+            start_time: None,
+            daily_date: None,
+            counts_in_statistics: true,
+            actions: Vec::new()
         }
     }
 
-    pub fn new(width: u32, height: u32, mines: u32) -> Board {
-        if width < 1 || height < 1 {
-            return Board::empty();
-        }
-        // Generate a 2-D vector with 'height' rows and 'width' cells per row
+    pub fn new(config: &BoardConfig) -> Board {
+        let width = config.width();
+        let height = config.height() * config.depth();
         // All cells are hidden and have value 0 at the start
         Board {
+            game_id: next_game_id(),
             width: width as usize,
             height: height as usize,
+            depth: config.depth() as usize,
             hidden_cell_count: (width * height) as usize,
             flag_count: 0,
-            mines: mines as usize,
+            flagged_mines: 0,
+            mines: config.mines() as usize,
+            mine_cells: 0,
+            max_cell_mines: config.max_cell_mines(),
+            anti_mines: config.anti_mines() as usize,
+            anti_mine_cells: 0,
+            anti_flag_count: 0,
+            liar: config.liar(),
+            move_interval: config.move_interval(),
+            mines_per_move: config.mines_per_move(),
+            pokes_until_move: config.move_interval(),
+            topology: config.topology(),
+            mode: config.mode(),
+            flags_match: if config.mode() == GameMode::Flags { Some(FlagsMatch::new()) } else { None },
             state: GameState::Playing,
-            cells: (0..height).map(|_| 
-                        (0..width).map(|_| 
-                            CellDetails {
-                                state: CellState::Hidden, 
-                                value: 0
-                            } )
-                .collect::<Vec<CellDetails>>())
-                .collect::<Vec<Vec<CellDetails>>>()
+            cells: vec![CellDetails {
+                state: CellState::Hidden,
+                content: CellContent::default(),
+                flags: 0,
+                lie: 0,
+                clue: None
+            }; (width * height) as usize],
+            preset: String::new(),
+            start_time: Some(Instant::now()),
+            daily_date: None,
+            counts_in_statistics: true,
+            actions: Vec::new()
         }
     }
 
@@ -77,8 +312,59 @@ impl Board {
     //     &self.cells
     // }
 
-    pub fn clone_cells(&self) -> Vec<Vec<CellDetails>> {
-        self.cells.to_vec()
+    pub fn get_game_id(&self) -> u32 {
+        self.game_id
+    }
+
+    /// Returns a copy of the cells with only what the player can see: the
+    /// content of every cell that isn't uncovered is replaced by an empty
+    /// one, and uncovered numbers are shown the way the player sees them. A
+    /// fuzzy number is left out, as only its clue is shown
+    pub fn clone_visible_cells(&self) -> Vec<Vec<CellDetails>> {
+        self.cells.chunks(self.width.max(1))
+            .map(|row| row.iter()
+                .map(|cell| match cell.state {
+                    CellState::ShowValue | CellState::ShowMineExploded =>
+                        CellDetails { content: shown_content(cell), lie: 0, ..*cell },
+                    state => CellDetails { state, content: CellContent::default(), flags: cell.flags, lie: 0, clue: None }
+                })
+                .collect())
+            .collect()
+    }
+
+    /// Returns a board with only what the player can see of this one, as
+    /// in `clone_visible_cells`, to work on without holding on to this one.
+    /// It doesn't count towards the statistics
+    pub fn visible_copy(&self) -> Board {
+        Board {
+            game_id: self.game_id,
+            width: self.width,
+            height: self.height,
+            depth: self.depth,
+            hidden_cell_count: self.hidden_cell_count,
+            flag_count: self.flag_count,
+            flagged_mines: self.flagged_mines,
+            mines: self.mines,
+            mine_cells: self.mine_cells,
+            max_cell_mines: self.max_cell_mines,
+            anti_mines: self.anti_mines,
+            anti_mine_cells: self.anti_mine_cells,
+            anti_flag_count: self.anti_flag_count,
+            liar: self.liar,
+            move_interval: self.move_interval,
+            mines_per_move: self.mines_per_move,
+            pokes_until_move: self.pokes_until_move,
+            topology: self.topology,
+            mode: self.mode,
+            flags_match: self.flags_match.clone(),
+            state: self.state,
+            cells: self.clone_visible_cells().concat(),
+            preset: self.preset.clone(),
+            start_time: self.start_time,
+            daily_date: self.daily_date.clone(),
+            counts_in_statistics: false,
+            actions: Vec::new()
+        }
     }
 
     pub fn board_empty(&self) -> bool {
@@ -93,50 +379,171 @@ impl Board {
         self.height
     }
 
-    pub fn get_value(&self, x: usize, y: usize) -> Result<u8, String> {
-        if self.board_empty() {
-            Err(String::from("Board is empty"))
-        } else if x >= self.width || y >= self.height {
-            Err(String::from("Cell coordinate out of bounds"))
+    /// Number of rows in a single layer
+    pub fn get_layer_height(&self) -> usize {
+        self.height / self.depth
+    }
+
+    /// The index of `(x, y)` in the cells, or `None` if it's not on the board.
+    /// This is the only place coordinates are checked, so the cells of an
+    /// index can be used without checking again
+    pub fn index(&self, x: usize, y: usize) -> Option<usize> {
+        if x < self.width && y < self.height {
+            Some(y * self.width + x)
         } else {
-            Ok(self.cells.get(y).unwrap().get(x).unwrap().value)
+            None
         }
     }
 
-    pub fn get_state(&self, x: usize, y: usize) -> Result<CellState, String> {
-        if self.board_empty() {
-            Err(String::from("Board is empty"))
-        } else if x >= self.width || y >= self.height {
-            Err(String::from("Cell coordinate out of bounds"))
-        } else {
-            Ok(self.cells.get(y).unwrap().get(x).unwrap().state)
+    /// The coordinates of the cell at `index`
+    pub fn position(&self, index: usize) -> (usize, usize) {
+        (index % self.width, index / self.width)
+    }
+
+    /// The cell at `index`, which must come from `index` or `neighbour_indices`
+    pub fn cell(&self, index: usize) -> &CellDetails {
+        &self.cells[index]
+    }
+
+    /// Returns the indices of the cells around the cell at `index`, not
+    /// including the cell itself. On a `Torus`, the neighbours wrap around
+    /// the edges. On a board with several layers, the cells at the same
+    /// place in the layers above and below are neighbours too, but the
+    /// layers don't wrap
+    pub fn neighbour_indices(&self, index: usize) -> impl Iterator<Item = usize> {
+        // Filled on the stack, so nothing is allocated for a lookup
+        let mut neighbours = [0; MAX_NEIGHBOURS];
+        let mut count = 0;
+
+        let (x, y) = self.position(index);
+        let width = self.width as isize;
+        let height = self.get_layer_height() as isize;
+        let (layer, y) = (y as isize / height, y as isize % height);
+        for z_offset in [-1, 0, 1] {
+            let z_check = layer + z_offset;
+            if z_check < 0 || z_check >= self.depth as isize { continue; }
+
+            for x_offset in [-1, 0, 1] {
+                for y_offset in [-1, 0, 1] {
+                    // Don't include the cell itself
+                    if x_offset == 0 && y_offset == 0 && z_offset == 0 { continue; }
+
+                    let x_check = x as isize + x_offset;
+                    let y_check = y + y_offset;
+                    let (x_check, y_check) = match self.topology {
+                        Topology::Rectangle => {
+                            if x_check < 0 || y_check < 0 || x_check >= width || y_check >= height { continue; }
+                            (x_check, y_check)
+                        },
+                        Topology::Torus => (x_check.rem_euclid(width), y_check.rem_euclid(height))
+                    };
+                    neighbours[count] = ((z_check * height + y_check) * width + x_check) as usize;
+                    count += 1;
+                }
+            }
         }
+        neighbours.into_iter().take(count)
     }
 
-    pub fn set_value(&mut self, x: usize, y: usize, value: u8) -> Result<(), String> {
-        if self.board_empty() {
-            return Err(String::from("Board is empty"));
+    /// Returns the coordinates of the cells around `(x, y)` in the order of
+    /// `neighbour_indices`, or none if `(x, y)` isn't on the board
+    pub fn neighbours(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        match self.index(x, y) {
+            Some(index) => self.neighbour_indices(index).map(|neighbour| self.position(neighbour)).collect(),
+            None => Vec::new()
         }
-        if x >= self.width || y >= self.height {
-            Err(String::from("Cell coordinate out of bounds"))
-        } else if value > 9 {
-            Err(String::from("Invalid cell value, must be between 0 and 9 inclusive"))
+    }
+
+    /// The error for coordinates that aren't on the board. It is only made
+    /// once a lookup failed, so lookups that succeed never allocate
+    fn out_of_bounds(&self) -> String {
+        if self.board_empty() {
+            String::from("Board is empty")
         } else {
-            self.cells.get_mut(y).unwrap().get_mut(x).unwrap().value = value;
-            Ok(())
+            String::from("Cell coordinate out of bounds")
         }
     }
 
-    pub fn set_state(&mut self, x: usize, y: usize, state: CellState) -> Result<(), String> {
-        if self.board_empty() {
-            return Err(String::from("Board is empty"));
+    pub fn get_content(&self, x: usize, y: usize) -> Result<CellContent, String> {
+        self.index(x, y).map(|index| self.cells[index].content).ok_or_else(|| self.out_of_bounds())
+    }
+
+    /// The content of `(x, y)` as the player sees it once it is uncovered,
+    /// with the lie of a liar board in its number
+    pub fn get_shown_content(&self, x: usize, y: usize) -> Result<CellContent, String> {
+        self.index(x, y).map(|index| shown_content(&self.cells[index])).ok_or_else(|| self.out_of_bounds())
+    }
+
+    /// The clue shown instead of the number of `(x, y)`, if it is fuzzy
+    pub fn get_clue(&self, x: usize, y: usize) -> Option<Clue> {
+        self.index(x, y).and_then(|index| self.cells[index].clue)
+    }
+
+    /// True if `(x, y)` holds at least one mine
+    pub fn is_mine(&self, x: usize, y: usize) -> bool {
+        self.index(x, y).is_some_and(|index| self.cells[index].content.is_mine())
+    }
+
+    pub fn get_flags(&self, x: usize, y: usize) -> Result<u8, String> {
+        self.index(x, y).map(|index| self.cells[index].flags).ok_or_else(|| self.out_of_bounds())
+    }
+
+    pub fn get_state(&self, x: usize, y: usize) -> Result<CellState, String> {
+        self.index(x, y).map(|index| self.cells[index].state).ok_or_else(|| self.out_of_bounds())
+    }
+
+    pub fn set_content(&mut self, x: usize, y: usize, content: CellContent) -> Result<(), String> {
+        let index = self.index(x, y).ok_or_else(|| self.out_of_bounds())?;
+        self.set_content_at(index, content)
+    }
+
+    /// Sets the content of the cell at `index`, keeping the mine counts up to date
+    pub fn set_content_at(&mut self, index: usize, content: CellContent) -> Result<(), String> {
+        if content == CellContent::Mines(0) || content.mine_count() > self.max_cell_mines {
+            return Err(format!("Invalid cell content, a cell holds 1 to {} mines", self.max_cell_mines));
         }
-        if x >= self.width || y >= self.height {
-            Err(String::from("Cell coordinate out of bounds"))
-        } else {
-            self.cells.get_mut(y).unwrap().get_mut(x).unwrap().state = state;
-            Ok(())
+        let cell = &mut self.cells[index];
+        match (cell.content.is_mine(), content.is_mine()) {
+            (false, true) => self.mine_cells += 1,
+            (true, false) => self.mine_cells -= 1,
+            _ => {}
+        }
+        match (cell.content.is_anti_mine(), content.is_anti_mine()) {
+            (false, true) => self.anti_mine_cells += 1,
+            (true, false) => self.anti_mine_cells -= 1,
+            _ => {}
         }
+        cell.content = content;
+        Ok(())
+    }
+
+    /// Sets how much the number of `(x, y)` is shown off by
+    pub fn set_lie(&mut self, x: usize, y: usize, lie: i8) -> Result<(), String> {
+        let index = self.index(x, y).ok_or_else(|| self.out_of_bounds())?;
+        self.cells[index].lie = lie;
+        Ok(())
+    }
+
+    /// Makes the number of `(x, y)` fuzzy by showing `clue` instead, or exact for `None`
+    pub fn set_clue(&mut self, x: usize, y: usize, clue: Option<Clue>) -> Result<(), String> {
+        let index = self.index(x, y).ok_or_else(|| self.out_of_bounds())?;
+        self.cells[index].clue = clue;
+        Ok(())
+    }
+
+    /// Sets the number of mines the player flagged in `(x, y)`
+    pub fn set_flags(&mut self, x: usize, y: usize, flags: u8) -> Result<(), String> {
+        let index = self.index(x, y).ok_or_else(|| self.out_of_bounds())?;
+        let cell = &mut self.cells[index];
+        self.flagged_mines = self.flagged_mines + flags as usize - cell.flags as usize;
+        cell.flags = flags;
+        Ok(())
+    }
+
+    pub fn set_state(&mut self, x: usize, y: usize, state: CellState) -> Result<(), String> {
+        let index = self.index(x, y).ok_or_else(|| self.out_of_bounds())?;
+        self.cells[index].state = state;
+        Ok(())
     }
 
     pub fn get_game_state(&self) -> GameState {
@@ -159,6 +566,19 @@ impl Board {
         self.flag_count -= 1;
     }
 
+    pub fn add_anti_flag(&mut self) {
+        self.anti_flag_count += 1;
+    }
+
+    pub fn subtract_anti_flag(&mut self) {
+        self.anti_flag_count -= 1;
+    }
+
+    /// Number of cells that are neither hidden nor flagged
+    pub fn get_revealed_cell_count(&self) -> usize {
+        self.width * self.height - self.hidden_cell_count - self.flag_count - self.anti_flag_count
+    }
+
     pub fn get_hidden_cell_count(&self) -> usize {
         self.hidden_cell_count
     }
@@ -175,15 +595,153 @@ impl Board {
     //     self.mines = mines
     // }
 
-    // pub fn get_mine_total(&self) -> usize {
-    //     self.mines
-    // }
+    pub fn get_mode(&self) -> GameMode {
+        self.mode
+    }
+
+    pub fn get_flags_match(&self) -> Option<&FlagsMatch> {
+        self.flags_match.as_ref()
+    }
+
+    pub fn get_flags_match_mut(&mut self) -> Option<&mut FlagsMatch> {
+        self.flags_match.as_mut()
+    }
+
+    /// Total of the mines in all cells
+    pub fn get_mine_total(&self) -> usize {
+        self.mines
+    }
+
+    /// Number of cells with at least one mine
+    pub fn get_mine_cell_count(&self) -> usize {
+        self.mine_cells
+    }
+
+    /// Most mines a cell on this board can hold
+    pub fn get_max_cell_mines(&self) -> u8 {
+        self.max_cell_mines
+    }
+
+    pub fn get_anti_mine_total(&self) -> usize {
+        self.anti_mines
+    }
+
+    /// True if the board only has cells with a single mine, so the solver
+    /// can reason about it
+    pub fn has_standard_mines(&self) -> bool {
+        self.max_cell_mines == 1 && self.anti_mines == 0
+    }
+
+    /// True if every number on the board is shown one higher or lower than it is
+    pub fn is_liar(&self) -> bool {
+        self.liar
+    }
+
+    pub fn moves_mines(&self) -> bool {
+        self.move_interval > 0
+    }
+
+    pub fn get_mines_per_move(&self) -> usize {
+        self.mines_per_move as usize
+    }
+
+    /// Number of pokes left before the mines move, if they ever do
+    pub fn get_pokes_until_move(&self) -> Option<u32> {
+        if self.moves_mines() { Some(self.pokes_until_move) } else { None }
+    }
+
+    /// Counts a poke towards the next time the mines move. Returns true if
+    /// they move now, and starts counting again
+    pub fn count_poke_until_move(&mut self) -> bool {
+        if !self.moves_mines() {
+            return false;
+        }
+        self.pokes_until_move -= 1;
+        if self.pokes_until_move == 0 {
+            self.pokes_until_move = self.move_interval;
+            return true;
+        }
+        false
+    }
+
+    pub fn get_preset(&self) -> &str {
+        &self.preset
+    }
+
+    pub fn set_preset(&mut self, preset: String) {
+        self.preset = preset
+    }
+
+    pub fn get_daily_date(&self) -> Option<&str> {
+        self.daily_date.as_deref()
+    }
+
+    pub fn set_daily_date(&mut self, date: Option<String>) {
+        self.daily_date = date
+    }
+
+    pub fn counts_in_statistics(&self) -> bool {
+        self.counts_in_statistics
+    }
+
+    pub fn exclude_from_statistics(&mut self) {
+        self.counts_in_statistics = false
+    }
+
+    pub fn record_action(&mut self, action: Action) {
+        self.actions.push(action)
+    }
+
+    pub fn get_actions(&self) -> &[Action] {
+        &self.actions
+    }
+
+    /// Time since the board was generated
+    pub fn get_elapsed_time(&self) -> Duration {
+        self.start_time.map_or(Duration::ZERO, |start| start.elapsed())
+    }
 
     pub fn get_remaining_mines(&self) -> isize {
-        self.mines as isize - self.flag_count as isize
+        self.mines as isize - self.flagged_mines as isize
+    }
+
+    pub fn get_remaining_anti_mines(&self) -> isize {
+        self.anti_mines as isize - self.anti_flag_count as isize
     }
 
+    /// True if only cells with mines or anti-mines are left hidden or flagged
     pub fn all_mines_found(&self) -> bool {
-        return self.hidden_cell_count as isize == (self.mines as isize - self.flag_count as isize) && self.flag_count <= self.mines
+        let hazard_cells = self.mine_cells + self.anti_mine_cells;
+        let flags = self.flag_count + self.anti_flag_count;
+        return self.hidden_cell_count as isize == (hazard_cells as isize - flags as isize) && flags <= hazard_cells
+    }
+}
+
+/// The content of `cell` with its lie added to its number. A fuzzy number
+/// isn't shown at all, so it is replaced by an empty cell
+fn shown_content(cell: &CellDetails) -> CellContent {
+    match cell.content {
+        CellContent::Number(_) if cell.clue.is_some() => CellContent::default(),
+        CellContent::Number(value) => CellContent::Number(value + cell.lie),
+        content => content
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::GenerationMode;
+
+    #[test]
+    fn torus_neighbours_wrap_around_the_edges() {
+        let config = BoardConfig::new(5, 4, 3, 2, 2, GenerationMode::SafeArea).unwrap();
+        let mut corner = Board::new(&config).neighbours(0, 0);
+        corner.sort_unstable();
+        assert_eq!(corner, vec![(0, 1), (1, 0), (1, 1)]);
+
+        let torus = Board::new(&config.with_topology(Topology::Torus).unwrap());
+        let mut corner = torus.neighbours(0, 0);
+        corner.sort_unstable();
+        assert_eq!(corner, vec![(0, 1), (0, 3), (1, 0), (1, 1), (1, 3), (4, 0), (4, 1), (4, 3)]);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,447 @@
+use serde::{Serialize, Deserialize};
+
+use crate::settings::GenerationMode;
+
+/// Smallest allowed width and height of a board
+pub const MIN_DIMENSION: u32 = 4;
+
+/// Largest allowed width and height of a board
+pub const MAX_DIMENSION: u32 = 100;
+
+/// Most layers a three-dimensional board can have
+pub const MAX_DEPTH: u32 = 10;
+
+/// Smallest allowed number of mines on a board
+pub const MIN_MINES: u32 = 1;
+
+/// Most mines a single cell can hold on a board with multi-mine cells
+pub const MAX_CELL_MINES: u8 = 3;
+
+/// Largest percentage of fuzzy numbers on a fog-of-war board
+pub const MAX_FUZZY_CLUES: u8 = 100;
+
+/// How the edges of the board are connected
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum Topology {
+    /// A regular board, where cells on the edge have fewer neighbours
+    #[default]
+    Rectangle,
+    /// The left edge borders the right edge and the top edge borders the
+    /// bottom edge, so every cell has 8 neighbours in its layer
+    Torus
+}
+
+/// The rules the game is played by
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum GameMode {
+    /// A single player has to uncover every cell without a mine
+    #[default]
+    Classic,
+    /// Two players take turns poking cells. Poking a mine claims it for the
+    /// player, who gets another turn. The first player to claim more than
+    /// half of the mines wins
+    Flags,
+    /// Some of the cells without a mine start uncovered, and nothing can be
+    /// poked. The player has to flag exactly all mines and submit the board
+    Puzzle
+}
+
+/// A board configuration as received from the outside, before validation
+#[derive(Deserialize)]
+pub struct UncheckedBoardConfig {
+    width: u32,
+    height: u32,
+    mines: u32,
+    first_x: u32,
+    first_y: u32,
+    #[serde(default)]
+    topology: Topology,
+    #[serde(default)]
+    generation_mode: Option<GenerationMode>,
+    #[serde(default)]
+    mode: GameMode,
+    #[serde(default)]
+    depth: Option<u32>,
+    #[serde(default)]
+    max_cell_mines: Option<u8>,
+    #[serde(default)]
+    anti_mines: u32,
+    #[serde(default)]
+    liar: bool,
+    #[serde(default)]
+    fuzzy_clues: u8,
+    #[serde(default)]
+    move_interval: u32,
+    #[serde(default)]
+    mines_per_move: Option<u32>
+}
+
+impl UncheckedBoardConfig {
+    /// Validates the config into a `BoardConfig`. If it has no generation
+    /// mode, `generation_mode` is used
+    pub fn check(self, generation_mode: GenerationMode) -> Result<BoardConfig, String> {
+        BoardConfig::new_layered(self.width, self.height, self.depth.unwrap_or(1), self.mines, self.first_x, self.first_y,
+                self.generation_mode.unwrap_or(generation_mode))?
+            .with_topology(self.topology)?
+            .with_mode(self.mode)?
+            .with_max_cell_mines(self.max_cell_mines.unwrap_or(1))?
+            .with_anti_mines(self.anti_mines)?
+            .with_liar(self.liar)?
+            .with_fuzzy_clues(self.fuzzy_clues)?
+            .with_moving_mines(self.move_interval, self.mines_per_move.unwrap_or(1))
+    }
+}
+
+/// Everything needed to generate a board. A `BoardConfig` can only be
+/// created through `BoardConfig::new` (or by deserializing, which calls it),
+/// so every instance is within the limits above and has room for all its mines.
+/// There is no board editor or file import yet; they should deserialize a
+/// `BoardConfig` too, rather than check the limits themselves
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(try_from = "UncheckedBoardConfig")]
+pub struct BoardConfig {
+    width: u32,
+    height: u32,
+    /// Number of layers, 1 for a flat board. The layers are stored below
+    /// each other, so the board has `height * depth` rows and `first_y`
+    /// counts the rows of all layers
+    depth: u32,
+    mines: u32,
+    first_x: u32,
+    first_y: u32,
+    topology: Topology,
+    generation_mode: GenerationMode,
+    mode: GameMode,
+    /// Most mines a cell can hold. `mines` is then the total of the mines
+    /// in all cells, which are spread over fewer cells
+    max_cell_mines: u8,
+    /// Number of anti-mines, each in a cell of its own
+    anti_mines: u32,
+    /// Every number is shown one higher or one lower than it is
+    liar: bool,
+    /// Percentage of the numbers that are shown as a range or a parity
+    /// instead, as far as the board stays solvable
+    fuzzy_clues: u8,
+    /// Number of pokes after which mines move, 0 if they never do
+    move_interval: u32,
+    /// Number of mines that move at a time
+    mines_per_move: u32
+}
+
+impl BoardConfig {
+    pub fn new(width: u32, height: u32, mines: u32, first_x: u32, first_y: u32,
+            generation_mode: GenerationMode) -> Result<BoardConfig, String> {
+        BoardConfig::new_layered(width, height, 1, mines, first_x, first_y, generation_mode)
+    }
+
+    /// Creates the config of a board with `depth` layers of `width` x
+    /// `height` cells. Every cell borders the cells around it in its own
+    /// layer and in the layers above and below, up to 26 cells
+    pub fn new_layered(width: u32, height: u32, depth: u32, mines: u32, first_x: u32, first_y: u32,
+            generation_mode: GenerationMode) -> Result<BoardConfig, String> {
+        check_size(width, height, depth, mines, generation_mode)?;
+        if first_x >= width || first_y >= height * depth {
+            return Err(format!("The first click ({}, {}) is outside the {}x{} board", first_x, first_y, width, height * depth));
+        }
+
+        let max_mines = width * height * depth - safe_zone_size(width, height, depth, first_x, first_y,
+            Topology::Rectangle, generation_mode);
+        if mines > max_mines {
+            return Err(format!("A first click at ({}, {}) leaves room for at most {} mines, but {} were requested",
+                first_x, first_y, max_mines, mines));
+        }
+
+        Ok(BoardConfig { width, height, depth, mines, first_x, first_y, topology: Topology::Rectangle, generation_mode,
+            mode: GameMode::Classic, max_cell_mines: 1, anti_mines: 0, liar: false, fuzzy_clues: 0,
+            move_interval: 0, mines_per_move: 0 })
+    }
+
+    /// Returns this config with the edges of the board connected as in
+    /// `topology`. On a `Torus`, every first click keeps a full safe zone
+    /// free, so there may be room for fewer mines
+    pub fn with_topology(self, topology: Topology) -> Result<BoardConfig, String> {
+        let max_cells = self.width * self.height * self.depth - safe_zone_size(self.width, self.height, self.depth,
+            self.first_x, self.first_y, topology, self.generation_mode);
+        if self.mines + self.anti_mines > max_cells {
+            return Err(format!("A first click at ({}, {}) of a {:?} leaves room for at most {} mines, but {} were requested",
+                self.first_x, self.first_y, topology, max_cells, self.mines + self.anti_mines));
+        }
+        BoardConfig { topology, ..self }.check_variants()
+    }
+
+    /// Returns this config with the game played by the rules of `mode`
+    pub fn with_mode(self, mode: GameMode) -> Result<BoardConfig, String> {
+        if mode == GameMode::Flags && self.mines < 2 {
+            return Err(String::from("A Flags match needs at least 2 mines"));
+        }
+        BoardConfig { mode, ..self }.check_variants()
+    }
+
+    /// Returns this config with up to `max_cell_mines` mines in a cell
+    pub fn with_max_cell_mines(self, max_cell_mines: u8) -> Result<BoardConfig, String> {
+        if !(1..=MAX_CELL_MINES).contains(&max_cell_mines) {
+            return Err(format!("A cell can hold 1 to {} mines, not {}", MAX_CELL_MINES, max_cell_mines));
+        }
+        BoardConfig { max_cell_mines, ..self }.check_variants()
+    }
+
+    /// Returns this config with `anti_mines` anti-mines besides the mines.
+    /// Every anti-mine needs a cell without a mine
+    pub fn with_anti_mines(self, anti_mines: u32) -> Result<BoardConfig, String> {
+        let max_cells = self.width * self.height * self.depth - safe_zone_size(self.width, self.height, self.depth,
+            self.first_x, self.first_y, self.topology, self.generation_mode);
+        if self.mines + anti_mines > max_cells {
+            return Err(format!("A first click at ({}, {}) leaves room for at most {} mines and anti-mines, but {} were requested",
+                self.first_x, self.first_y, max_cells, self.mines + anti_mines));
+        }
+        BoardConfig { anti_mines, ..self }.check_variants()
+    }
+
+    /// Returns this config with every number shown off by one if `liar` is true
+    pub fn with_liar(self, liar: bool) -> Result<BoardConfig, String> {
+        BoardConfig { liar, ..self }.check_variants()
+    }
+
+    /// Returns this config with `fuzzy_clues` percent of the numbers shown
+    /// as a range or a parity
+    pub fn with_fuzzy_clues(self, fuzzy_clues: u8) -> Result<BoardConfig, String> {
+        if fuzzy_clues > MAX_FUZZY_CLUES {
+            return Err(format!("At most {}% of the numbers can be fuzzy, not {}%", MAX_FUZZY_CLUES, fuzzy_clues));
+        }
+        BoardConfig { fuzzy_clues, ..self }.check_variants()
+    }
+
+    /// Returns this config with `mines_per_move` mines moving to other
+    /// hidden cells after every `move_interval` pokes. A `move_interval`
+    /// of 0 keeps the mines in place
+    pub fn with_moving_mines(self, move_interval: u32, mines_per_move: u32) -> Result<BoardConfig, String> {
+        if move_interval == 0 {
+            return BoardConfig { move_interval: 0, mines_per_move: 0, ..self }.check_variants();
+        }
+        if mines_per_move == 0 || mines_per_move > self.mines {
+            return Err(format!("1 to {} mines can move at a time, not {}", self.mines, mines_per_move));
+        }
+        BoardConfig { move_interval, mines_per_move, ..self }.check_variants()
+    }
+
+    /// Checks that the variant rules of the config can be played together.
+    /// Most are only for classic games, and only multi-mine cells and
+    /// anti-mines can be combined with each other
+    fn check_variants(self) -> Result<BoardConfig, String> {
+        // The name of each variant for the start and the middle of a sentence
+        let variants = [
+            ("Multi-mine cells", "multi-mine cells", self.max_cell_mines > 1, true),
+            ("Anti-mines", "anti-mines", self.anti_mines > 0, true),
+            ("Lying numbers", "lying numbers", self.liar, true),
+            ("Fuzzy numbers", "fuzzy numbers", self.fuzzy_clues > 0, false),
+            ("Moving mines", "moving mines", self.move_interval > 0, true)
+        ];
+        let active: Vec<_> = variants.iter().filter(|(_, _, active, _)| *active).collect();
+
+        if self.mode != GameMode::Classic {
+            if let Some((name, _, _, _)) = active.iter().find(|(_, _, _, classic_only)| *classic_only) {
+                return Err(format!("{} are only available in classic games", name));
+            }
+        }
+        if let [(first, _, _, _), (_, second, _, _), ..] = active[..] {
+            if !(active.len() == 2 && self.max_cell_mines > 1 && self.anti_mines > 0) {
+                return Err(format!("{} can't be combined with {}", first, second));
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    pub fn mines(&self) -> u32 {
+        self.mines
+    }
+
+    pub fn first_x(&self) -> u32 {
+        self.first_x
+    }
+
+    pub fn first_y(&self) -> u32 {
+        self.first_y
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn generation_mode(&self) -> GenerationMode {
+        self.generation_mode
+    }
+
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
+
+    pub fn max_cell_mines(&self) -> u8 {
+        self.max_cell_mines
+    }
+
+    pub fn anti_mines(&self) -> u32 {
+        self.anti_mines
+    }
+
+    pub fn liar(&self) -> bool {
+        self.liar
+    }
+
+    pub fn fuzzy_clues(&self) -> u8 {
+        self.fuzzy_clues
+    }
+
+    pub fn move_interval(&self) -> u32 {
+        self.move_interval
+    }
+
+    pub fn mines_per_move(&self) -> u32 {
+        self.mines_per_move
+    }
+
+    /// True if the board is played without any variant rules: one mine in a
+    /// cell, no anti-mines, exact numbers that tell the truth and mines that stay put
+    pub fn is_standard(&self) -> bool {
+        self.max_cell_mines == 1 && self.anti_mines == 0 && !self.liar && self.fuzzy_clues == 0 && self.move_interval == 0
+    }
+}
+
+impl TryFrom<UncheckedBoardConfig> for BoardConfig {
+    type Error = String;
+
+    fn try_from(c: UncheckedBoardConfig) -> Result<BoardConfig, String> {
+        c.check(GenerationMode::default())
+    }
+}
+
+/// Checks the dimensions and mine count of a board without knowing the
+/// first click yet. The mine count must leave room for the largest possible
+/// safe zone, so any first click is valid
+pub fn check_board_size(width: u32, height: u32, mines: u32, generation_mode: GenerationMode) -> Result<(), String> {
+    check_size(width, height, 1, mines, generation_mode)
+}
+
+/// Checks the size of a board with `depth` layers, like `check_board_size`
+fn check_size(width: u32, height: u32, depth: u32, mines: u32, generation_mode: GenerationMode) -> Result<(), String> {
+    if width < MIN_DIMENSION || height < MIN_DIMENSION {
+        return Err(format!("The board must be at least {}x{} cells, but is {}x{}", MIN_DIMENSION, MIN_DIMENSION, width, height));
+    }
+    if width > MAX_DIMENSION || height > MAX_DIMENSION {
+        return Err(format!("The board can be at most {}x{} cells, but is {}x{}", MAX_DIMENSION, MAX_DIMENSION, width, height));
+    }
+    if !(1..=MAX_DEPTH).contains(&depth) {
+        return Err(format!("The board can have 1 to {} layers, not {}", MAX_DEPTH, depth));
+    }
+    if mines < MIN_MINES {
+        return Err(format!("The board needs at least {} mine", MIN_MINES));
+    }
+
+    let max_mines = max_mines(width, height, depth, generation_mode);
+    if mines > max_mines {
+        return Err(format!("A {}x{}x{} board can have at most {} mines, but {} were requested",
+            width, height, depth, max_mines, mines));
+    }
+    Ok(())
+}
+
+/// The largest mine count that is valid for any first click on the board
+pub fn max_mines(width: u32, height: u32, depth: u32, generation_mode: GenerationMode) -> u32 {
+    // Boards are at least 4x4, so the cell at (1, 1) has the largest safe
+    // zone, in the second layer if there is more than one. That is as large
+    // as the safe zone of any cell of a `Torus`
+    let first_y = if depth > 1 { height + 1 } else { 1 };
+    width * height * depth - safe_zone_size(width, height, depth, 1, first_y, Topology::Rectangle, generation_mode)
+}
+
+/// Number of cells that are kept free of mines for a first click at `(first_x, first_y)`.
+/// This is 9 for a cell in the centre, 6 for a cell on the edge and 4 for a
+/// corner cell of a `Rectangle`, or 1 if only the first cell is kept safe.
+/// The layers above and below the first click add as many cells each
+fn safe_zone_size(width: u32, height: u32, depth: u32, first_x: u32, first_y: u32,
+        topology: Topology, generation_mode: GenerationMode) -> u32 {
+    let (first_z, first_y) = (first_y / height, first_y % height);
+    let layers = (first_z + 1).min(depth - 1) - first_z.saturating_sub(1) + 1;
+    match (generation_mode, topology) {
+        (GenerationMode::SafeCell, _) => 1,
+        (GenerationMode::SafeArea, Topology::Torus) => 9 * layers,
+        (GenerationMode::SafeArea, Topology::Rectangle) => {
+            let columns = (first_x + 1).min(width - 1) - first_x.saturating_sub(1) + 1;
+            let rows = (first_y + 1).min(height - 1) - first_y.saturating_sub(1) + 1;
+            columns * rows * layers
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_outside_the_limits_are_rejected() {
+        assert!(check_board_size(MIN_DIMENSION, MIN_DIMENSION, 1, GenerationMode::SafeArea).is_ok());
+        assert!(check_board_size(MAX_DIMENSION, MAX_DIMENSION, 1, GenerationMode::SafeArea).is_ok());
+        assert!(check_board_size(MIN_DIMENSION - 1, MIN_DIMENSION, 1, GenerationMode::SafeArea).is_err());
+        assert!(check_board_size(MIN_DIMENSION, MAX_DIMENSION + 1, 1, GenerationMode::SafeArea).is_err());
+        assert!(check_board_size(9, 9, MIN_MINES - 1, GenerationMode::SafeArea).is_err());
+        assert!(BoardConfig::new_layered(9, 9, 0, 10, 4, 4, GenerationMode::SafeArea).is_err());
+        assert!(BoardConfig::new_layered(9, 9, MAX_DEPTH, 10, 4, 4, GenerationMode::SafeArea).is_ok());
+        assert!(BoardConfig::new_layered(9, 9, MAX_DEPTH + 1, 10, 4, 4, GenerationMode::SafeArea).is_err());
+    }
+
+    #[test]
+    fn mines_leave_room_for_the_safe_zone() {
+        // Any first click has to fit, so the 9 cells around a middle cell are kept free
+        assert_eq!(max_mines(9, 9, 1, GenerationMode::SafeArea), 72);
+        assert_eq!(max_mines(9, 9, 1, GenerationMode::SafeCell), 80);
+        assert!(check_board_size(9, 9, 72, GenerationMode::SafeArea).is_ok());
+        assert!(check_board_size(9, 9, 73, GenerationMode::SafeArea).is_err());
+
+        // A first click in a corner keeps fewer cells free, but doesn't allow more mines
+        assert!(BoardConfig::new(9, 9, 72, 0, 0, GenerationMode::SafeArea).is_ok());
+        assert!(BoardConfig::new(9, 9, 73, 0, 0, GenerationMode::SafeArea).is_err());
+        assert_eq!(safe_zone_size(9, 9, 1, 0, 0, Topology::Rectangle, GenerationMode::SafeArea), 4);
+        assert_eq!(safe_zone_size(9, 9, 1, 4, 0, Topology::Rectangle, GenerationMode::SafeArea), 6);
+        assert_eq!(safe_zone_size(9, 9, 3, 4, 13, Topology::Rectangle, GenerationMode::SafeArea), 27);
+    }
+
+    #[test]
+    fn a_torus_keeps_a_full_safe_zone_around_any_first_click() {
+        let corner = BoardConfig::new(9, 9, 72, 0, 0, GenerationMode::SafeArea).unwrap();
+        assert!(corner.with_topology(Topology::Torus).is_ok());
+        assert!(corner.with_anti_mines(5).unwrap().with_topology(Topology::Torus).is_err());
+        assert_eq!(safe_zone_size(9, 9, 1, 0, 0, Topology::Torus, GenerationMode::SafeArea), 9);
+        assert_eq!(safe_zone_size(9, 9, 1, 0, 0, Topology::Torus, GenerationMode::SafeCell), 1);
+    }
+
+    #[test]
+    fn first_click_must_be_on_the_board() {
+        assert!(BoardConfig::new(9, 9, 10, 9, 0, GenerationMode::SafeArea).is_err());
+        assert!(BoardConfig::new(9, 9, 10, 0, 9, GenerationMode::SafeArea).is_err());
+        assert!(BoardConfig::new_layered(9, 9, 2, 10, 0, 17, GenerationMode::SafeArea).is_ok());
+    }
+
+    #[test]
+    fn variants_are_checked() {
+        let config = BoardConfig::new(9, 9, 10, 4, 4, GenerationMode::SafeArea).unwrap();
+        assert!(config.with_max_cell_mines(MAX_CELL_MINES + 1).is_err());
+        assert!(config.with_fuzzy_clues(MAX_FUZZY_CLUES + 1).is_err());
+        assert!(config.with_anti_mines(63).is_err());
+        assert!(config.with_moving_mines(3, 11).is_err());
+        assert!(config.with_liar(true).unwrap().with_fuzzy_clues(10).is_err());
+        assert!(config.with_liar(true).unwrap().with_mode(GameMode::Puzzle).is_err());
+        assert!(config.with_max_cell_mines(2).unwrap().with_anti_mines(5).is_ok());
+        assert!(BoardConfig::new(9, 9, 1, 4, 4, GenerationMode::SafeArea).unwrap().with_mode(GameMode::Flags).is_err());
+    }
+}
@@ -1,109 +1,522 @@
-use crate::board::{CellDetails, Board};
+use std::time::{Duration, Instant};
+
+use serde::{Serialize, Deserialize};
+
+use crate::board::{CellContent, CellState, Board, Clue};
+use crate::boardconfig::{BoardConfig, UncheckedBoardConfig};
+use crate::difficulty::{self, Difficulty};
+use crate::events;
+use crate::settings::{self, GenerationMode};
 use crate::CURRENT_BOARD;
 
+use rand::Rng;
 use rand::seq::SliceRandom;
 use rand::thread_rng;
 
+/// Number of boards generated at most when looking for one within a
+/// difficulty range
+const MAX_RATING_ATTEMPTS: u32 = 50;
+
+/// Time after which no more boards are generated when looking for one
+/// within a difficulty range. Rating a large, dense board can take a while
+const MAX_RATING_TIME: Duration = Duration::from_secs(2);
+
+/// Time after which no more numbers are made fuzzy. Every number that is
+/// tried means solving the whole board again
+const MAX_FUZZY_TIME: Duration = Duration::from_secs(1);
+
+/// A new board for the front-end, with how hard it is. The cells are left
+/// out, as they are all hidden and their content is secret. Uncovered cells
+/// come with the changes of every poke
+#[derive(Serialize)]
+pub struct GeneratedBoard {
+    width: usize,
+    /// Number of rows of all layers together
+    height: usize,
+    game_id: u32,
+    /// Only rated for boards without variant rules, which the solver can't
+    /// rate from a first click
+    difficulty: Option<Difficulty>
+}
+
+/// The board the front-end asks for: its config, and optionally the range
+/// of difficulty ratings it should be in
+#[derive(Deserialize)]
+pub struct BoardRequest {
+    #[serde(flatten)]
+    config: UncheckedBoardConfig,
+    #[serde(default)]
+    min_rating: Option<u32>,
+    #[serde(default)]
+    max_rating: Option<u32>
+}
+
 #[tauri::command]
 /// Generates a new board with the given dimensions, where the cell at
-/// `(first_x, first_y)` is guaranteed to be empty. (value = 0)
+/// `(first_x, first_y)` and the cells around it are free of mines, so the
+/// first poke opens an area. If the generation mode is `SafeCell`, only that
+/// cell is guaranteed to not be a mine.
 /// The maximum number of mines a board can have is `width * height - 9` for
 /// start-cells in the centre, `... - 6` for start-cells on the edge and
-/// `... - 4` for start-cells in a corner. The mine count is capped at that
-/// value. 
+/// `... - 4` for start-cells in a corner. On a `Torus` (see `topology`),
+/// every start-cell counts as one in the centre. Configurations
+/// outside of the limits in `boardconfig` are rejected with the reason.
+/// The generation mode of the settings is used, unless the request has one.
+/// If `depth` is above 1, the board has that many layers of `width` x
+/// `height` cells, stored below each other, and `first_y` counts the rows
+/// of all layers. The safe zone then includes the layers above and below.
+/// `mode` picks the rules the game is played by, `Classic` if not given.
+/// If `max_cell_mines` is above 1, cells can hold up to that many mines and
+/// `mines` is the total of the mines in all cells. `anti_mines` anti-mines
+/// are placed besides the mines, in cells of their own. If `liar` is true,
+/// every number is shown one higher or one lower than it is. `fuzzy_clues`
+/// is the percentage of the numbers shown as a range or a parity instead.
+/// If `move_interval` is given, `mines_per_move` mines move to other hidden
+/// cells away from the uncovered ones after every that many pokes.
+/// If `min_rating` or `max_rating` are given, boards are generated until one
+/// has a difficulty rating within them. If none does after a number of
+/// attempts, the board closest to the range is used
 /// 
 /// This function will not poke the start cell; all cells are Hidden after generation
-pub fn generate_board(width: u32, height: u32, mines: u32, first_x: u32, first_y: u32) -> Vec<Vec<CellDetails>> {
-    if width == 0 || height == 0 || first_x >= width || first_y >= height {
-        println!("Invalid parameters to generate board :(");
-        return Vec::new();
+pub fn generate_board(request: BoardRequest) -> Result<GeneratedBoard, String> {
+    let config = request.config.check(settings::get_generation_mode())?;
+    let min_rating = request.min_rating.unwrap_or(0);
+    let max_rating = request.max_rating.unwrap_or(u32::MAX);
+    if min_rating > max_rating {
+        return Err(format!("The minimum rating {} is above the maximum rating {}", min_rating, max_rating));
+    }
+    if !config.is_standard() {
+        if min_rating > 0 || max_rating < u32::MAX {
+            return Err(String::from("Boards with variant rules can't be rated"));
+        }
+        let mut board = create_board(&config, &mut thread_rng());
+        board.set_preset(settings::preset_name_for(&config));
+        let (width, height) = (board.get_width(), board.get_height());
+        return Ok(GeneratedBoard { width, height, game_id: set_current_board(board)?, difficulty: None });
+    }
+
+    // Keep the board that is the fewest rating points outside of the range
+    let mut best: Option<(u32, Board, Difficulty)> = None;
+    let mut rng = thread_rng();
+    let start = Instant::now();
+    for _ in 0..MAX_RATING_ATTEMPTS {
+        let board = create_board(&config, &mut rng);
+        let difficulty = difficulty::rate_board(&board, config.first_x() as usize, config.first_y() as usize);
+        let distance = min_rating.saturating_sub(difficulty.rating()) + difficulty.rating().saturating_sub(max_rating);
+        if best.as_ref().is_none_or(|(best_distance, _, _)| distance < *best_distance) {
+            best = Some((distance, board, difficulty));
+        }
+        if distance == 0 || start.elapsed() > MAX_RATING_TIME { break; }
     }
+    let Some((_, mut board, difficulty)) = best else {
+        return Err(String::from("Couldn't generate a board"));
+    };
+    board.set_preset(settings::preset_name_for(&config));
+
+    let (width, height) = (board.get_width(), board.get_height());
+    Ok(GeneratedBoard { width, height, game_id: set_current_board(board)?, difficulty: Some(difficulty) })
+}
+
+/// Creates a board with mines and numbers for `config`, taking all random
+/// choices from `rng`. The same config and seeded `rng` give the same board
+pub fn create_board(config: &BoardConfig, rng: &mut impl Rng) -> Board {
+    let mut board = Board::new(config);
 
-    let mut board = Board::new(width, height, mines);
-    
     // Add mines to the generated board
-    add_mines(&mut board, mines, first_x, first_y);
+    add_mines(&mut board, config, rng);
 
     // Add values
     calculate_numbers(&mut board);
 
-    // Create a copy of the board
-    let board_copy = board.clone_cells();
+    if config.liar() {
+        add_lies(&mut board, rng);
+    }
+    if config.fuzzy_clues() > 0 {
+        add_fuzzy_clues(&mut board, config, rng);
+    }
+
+    board
+}
+
+/// Creates a small practice position from `template`, one string per row.
+/// `.` is an uncovered cell, `F` a flagged mine, `*` and `m` hidden mines,
+/// `-` and `s` hidden safe cells and `?` a hidden cell that is a mine half
+/// of the time. The numbers are calculated from the mines
+pub fn create_position(template: &[Vec<char>], rng: &mut impl Rng) -> Result<Board, String> {
+    let rows: Vec<Vec<char>> = template.iter()
+        .map(|row| row.iter().map(|c| match c {
+            '?' if rng.gen_bool(0.5) => 'm',
+            '?' => 's',
+            c => *c
+        }).collect())
+        .collect();
+    let height = rows.len();
+    let width = rows.first().map_or(0, |row| row.len());
+    if rows.iter().any(|row| row.len() != width) {
+        return Err(String::from("All rows of a position must be equally long"));
+    }
+
+    let mines = rows.iter().flatten().filter(|c| matches!(c, 'F' | '*' | 'm')).count();
+    let Some(first) = rows.iter().flatten().position(|c| *c == '.') else {
+        return Err(String::from("A position needs at least one uncovered cell"));
+    };
+    let config = BoardConfig::new(width as u32, height as u32, mines as u32, (first % width) as u32, (first / width) as u32,
+        GenerationMode::SafeCell)?;
+    let mut board = Board::new(&config);
+
+    add_template_mines(&mut board, &rows);
+    calculate_numbers(&mut board);
+
+    for (y, row) in rows.iter().enumerate() {
+        for (x, c) in row.iter().enumerate() {
+            let state = match c {
+                '.' => CellState::ShowValue,
+                'F' => CellState::Flagged,
+                _ => continue
+            };
+            board.set_state(x, y, state)?;
+            board.subtract_hidden_cell();
+            if state == CellState::Flagged {
+                board.add_flag();
+                board.set_flags(x, y, 1)?;
+            }
+        }
+    }
+    Ok(board)
+}
+
+/// Makes `board` the current board, and returns its game ID
+pub fn set_current_board(board: Board) -> Result<u32, String> {
+    let (game_id, game_state) = (board.get_game_id(), board.get_game_state());
 
     // Set the generated board as the current board
     if let Ok(mut current_board) = CURRENT_BOARD.lock() {
         *current_board = board;
-        
-        // Return the copy to the front-end
-        return board_copy;
+        events::game_state_changed(game_id, game_state);
+        return Ok(game_id);
     }
     println!("ERROR: Couldn't lock current board variable :(");
-    return Vec::new();
+    return Err(String::from("Couldn't store the generated board"));
 }
 
-/// Sets `mines` number of cells to have a mine in it.
-/// Ensures that there are no mines around the provided
-/// cell at (`start_x`, `start_y`)
-fn add_mines(board: &mut Board, mines: u32, start_x: u32, start_y: u32) {
+/// Sets `config.mines()` number of cells to have a mine in it, or spreads
+/// that many mines over fewer cells on a board with multi-mine cells. Then
+/// puts the anti-mines in cells that are still empty.
+/// Ensures that there are no mines around the first clicked cell,
+/// or only not in that cell for `SafeCell`
+fn add_mines(board: &mut Board, config: &BoardConfig, rng: &mut impl Rng) {
+    let mut safe = vec![false; board.get_width() * board.get_height()];
+    if let Some(start) = board.index(config.first_x() as usize, config.first_y() as usize) {
+        safe[start] = true;
+        if config.generation_mode() == GenerationMode::SafeArea {
+            for neighbour in board.neighbour_indices(start) {
+                safe[neighbour] = true;
+            }
+        }
+    }
+
+    // Column by column, which the mines of seeded boards depend on
     let mut all_possible_cells: Vec<(usize, usize)> = Vec::with_capacity(board.get_height() * board.get_width());
     for x in 0..board.get_width() {
         for y in 0..board.get_height() {
-            if !borders_cell(x as i32, y as i32, start_x as i32, start_y as i32) {
+            if !safe[y * board.get_width() + x] {
                 all_possible_cells.push((x, y));
             }
         }
     }
 
-    let sample = all_possible_cells.choose_multiple(&mut thread_rng(), mines as usize);
+    // The config is validated, so there is always room for all mines
+    let mines = config.mines() as usize;
+    if config.max_cell_mines() == 1 {
+        let sample = all_possible_cells.choose_multiple(rng, mines);
 
-    for (x, y) in sample {
-        if let Err(err) = board.set_value(*x, *y, 9) {
+        for (x, y) in sample {
+            if let Err(err) = board.set_content(*x, *y, CellContent::Mines(1)) {
+                println!("ERROR (add_mines): {}", err);
+            }
+        }
+    } else {
+        add_multi_mines(board, config, &all_possible_cells, rng);
+    }
+
+    if config.anti_mines() > 0 {
+        let empty_cells: Vec<(usize, usize)> = all_possible_cells.into_iter()
+            .filter(|(x, y)| !board.is_mine(*x, *y))
+            .collect();
+        for (x, y) in empty_cells.choose_multiple(rng, config.anti_mines() as usize) {
+            if let Err(err) = board.set_content(*x, *y, CellContent::AntiMine) {
+                println!("ERROR (add_mines): {}", err);
+            }
+        }
+    }
+}
+
+/// Spreads `config.mines()` mines over `all_possible_cells`, with up to
+/// `config.max_cell_mines()` in a cell
+fn add_multi_mines(board: &mut Board, config: &BoardConfig, all_possible_cells: &[(usize, usize)], rng: &mut impl Rng) {
+    let mines = config.mines() as usize;
+
+    // Pick how many cells hold the mines, give each of them one mine and
+    // hand out the rest to cells that have room for more
+    let max_cell_mines = config.max_cell_mines() as usize;
+    let fewest_cells = mines.div_ceil(max_cell_mines);
+    let cell_count = rng.gen_range(fewest_cells..=mines.min(all_possible_cells.len()));
+    let cells: Vec<(usize, usize)> = all_possible_cells.choose_multiple(rng, cell_count).copied().collect();
+    let mut counts = vec![1; cell_count];
+    let mut left = mines - cell_count;
+    while left > 0 {
+        let i = rng.gen_range(0..cell_count);
+        if counts[i] < max_cell_mines {
+            counts[i] += 1;
+            left -= 1;
+        }
+    }
+
+    for ((x, y), count) in cells.into_iter().zip(counts) {
+        if let Err(err) = board.set_content(x, y, CellContent::Mines(count as u8)) {
             println!("ERROR (add_mines): {}", err);
         }
     }
 }
 
+/// Moves `board.get_mines_per_move()` mines from hidden cells that don't
+/// touch an uncovered cell to other such cells, and updates the numbers
+/// around them. Uncovered numbers don't change, as none of them touch those
+/// cells; if one does anyway, the move is undone. Returns the number of
+/// mines that moved
+pub fn move_mines(board: &mut Board, rng: &mut impl Rng) -> usize {
+    let mut mines = Vec::new();
+    let mut empty = Vec::new();
+    for y in 0..board.get_height() {
+        for x in 0..board.get_width() {
+            if board.get_state(x, y) != Ok(CellState::Hidden) { continue; }
+            let touches_uncovered = board.neighbours(x, y).into_iter()
+                .any(|(nx, ny)| board.get_state(nx, ny) == Ok(CellState::ShowValue));
+            if touches_uncovered { continue; }
+            if board.is_mine(x, y) {
+                mines.push((x, y));
+            } else {
+                empty.push((x, y));
+            }
+        }
+    }
+
+    let count = board.get_mines_per_move().min(mines.len()).min(empty.len());
+    let from: Vec<(usize, usize)> = mines.choose_multiple(rng, count).copied().collect();
+    let to: Vec<(usize, usize)> = empty.choose_multiple(rng, count).copied().collect();
+
+    // Every cell whose content can change, with its content now
+    let mut affected: Vec<(usize, usize)> = from.iter().chain(&to)
+        .flat_map(|(x, y)| board.neighbours(*x, *y).into_iter().chain([(*x, *y)]))
+        .collect();
+    affected.sort_unstable();
+    affected.dedup();
+    let before: Vec<CellContent> = affected.iter().map(|(x, y)| board.get_content(*x, *y).unwrap_or_default()).collect();
+
+    for (x, y) in &from {
+        let _ = board.set_content(*x, *y, CellContent::Number(0));
+    }
+    for (x, y) in &to {
+        let _ = board.set_content(*x, *y, CellContent::Mines(1));
+    }
+    for (x, y) in &affected {
+        if !board.is_mine(*x, *y) {
+            let _ = board.set_content(*x, *y, CellContent::Number(count_surrounding_mines(*x, *y, board)));
+        }
+    }
+
+    if !uncovered_numbers_match(board) {
+        println!("ERROR (bg.mm): Moving {} mines changed an uncovered number, the move is undone", count);
+        for ((x, y), content) in affected.iter().zip(before) {
+            let _ = board.set_content(*x, *y, content);
+        }
+        return 0;
+    }
+    count
+}
+
+/// True if every uncovered number still counts the mines around it
+fn uncovered_numbers_match(board: &Board) -> bool {
+    (0..board.get_height()).all(|y| (0..board.get_width()).all(|x| {
+        board.get_state(x, y) != Ok(CellState::ShowValue)
+            || match board.get_content(x, y) {
+                Ok(CellContent::Number(value)) => value == count_surrounding_mines(x, y, board),
+                _ => true
+            }
+    }))
+}
+
+/// Puts a mine in every cell that is a mine in the `rows` of a position
+fn add_template_mines(board: &mut Board, rows: &[Vec<char>]) {
+    for (y, row) in rows.iter().enumerate() {
+        for (x, c) in row.iter().enumerate() {
+            if matches!(c, 'F' | '*' | 'm') {
+                if let Err(err) = board.set_content(x, y, CellContent::Mines(1)) {
+                    println!("ERROR (bg.atm): {}", err);
+                }
+            }
+        }
+    }
+}
+
 /// Calculate the numbers of empty cells when the mines have been set
 fn calculate_numbers(board: &mut Board) {
-    for x in 0..board.get_width() {
-        for y in 0..board.get_height() {
-            // Don't overwrite the mines we just set
-            if board.get_value(x, y) == Ok(0) {
-                if let Err(e) = board.set_value(x, y, count_surrounding_mines(x, y, &board)) {
-                    println!("ERROR (bg.cn): Could not set value of cell ({}, {}), error: {}", x, y, e);
+    for index in 0..board.get_width() * board.get_height() {
+        // Don't overwrite the mines we just set
+        if board.cell(index).content == CellContent::Number(0) {
+            let value = board.neighbour_indices(index).map(|neighbour| board.cell(neighbour).content.weight()).sum();
+            if let Err(e) = board.set_content_at(index, CellContent::Number(value)) {
+                println!("ERROR (bg.cn): Could not set value of cell {}, error: {}", index, e);
+            }
+        }
+    }
+}
+
+/// Picks for every number whether it is shown one higher or one lower. A
+/// number that can't be lower (0) or higher (all neighbours are mines) has
+/// only one choice
+fn add_lies(board: &mut Board, rng: &mut impl Rng) {
+    for y in 0..board.get_height() {
+        for x in 0..board.get_width() {
+            let Ok(CellContent::Number(value)) = board.get_content(x, y) else { continue; };
+            let neighbours = board.neighbours(x, y).len() as i8;
+            let lie = if value == 0 {
+                1
+            } else if value == neighbours || rng.gen_bool(0.5) {
+                -1
+            } else {
+                1
+            };
+            if let Err(e) = board.set_lie(x, y, lie) {
+                println!("ERROR (bg.al): Could not set the lie of cell ({}, {}), error: {}", x, y, e);
+            }
+        }
+    }
+}
+
+/// Shows `config.fuzzy_clues()` percent of the numbers as a range or a
+/// parity. The numbers are tried in a random order, and a number stays
+/// exact if a fuzzy one would make the board need more guesses
+fn add_fuzzy_clues(board: &mut Board, config: &BoardConfig, rng: &mut impl Rng) {
+    let (first_x, first_y) = (config.first_x() as usize, config.first_y() as usize);
+    let mut numbers: Vec<(usize, usize, u8)> = Vec::new();
+    for y in 0..board.get_height() {
+        for x in 0..board.get_width() {
+            // An empty cell opens the cells around it, so it's always exact
+            if let Ok(CellContent::Number(value)) = board.get_content(x, y) {
+                if value > 0 {
+                    numbers.push((x, y, value as u8));
                 }
             }
         }
     }
+    numbers.shuffle(rng);
+
+    let target = numbers.len() * config.fuzzy_clues() as usize / 100;
+    let guesses = difficulty::rate_board(board, first_x, first_y).forced_guesses();
+    let start = Instant::now();
+    let mut fuzzy = 0;
+    for (x, y, value) in numbers {
+        if fuzzy == target || start.elapsed() > MAX_FUZZY_TIME { break; }
+
+        let clue = match rng.gen_range(0..3) {
+            0 => Clue::Range(value - 1, value),
+            1 => Clue::Range(value, value + 1),
+            _ if value.is_multiple_of(2) => Clue::Even,
+            _ => Clue::Odd
+        };
+        if let Err(e) = board.set_clue(x, y, Some(clue)) {
+            println!("ERROR (bg.afc): Could not set the clue of cell ({}, {}), error: {}", x, y, e);
+            continue;
+        }
+        if difficulty::rate_board(board, first_x, first_y).forced_guesses() > guesses {
+            let _ = board.set_clue(x, y, None);
+        } else {
+            fuzzy += 1;
+        }
+    }
+}
+
+/// Counts the mines around `(x, y)`, minus the anti-mines
+fn count_surrounding_mines(x: usize, y: usize, board: &Board) -> i8 {
+    match board.index(x, y) {
+        Some(index) => board.neighbour_indices(index).map(|neighbour| board.cell(neighbour).content.weight()).sum(),
+        None => 0
+    }
 }
 
-fn count_surrounding_mines(x: usize, y: usize, board: &Board) -> u8 {
-    let mut mine_count = 0u8;
-    let x_int = x as i32;
-    let y_int = y as i32;
-    for x_offset in [-1, 0, 1] {
-        for y_offset in [-1, 0, 1] {
-            // Don't check the cell itself
-            if x_offset == 0 && y_offset == 0 { continue; }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    fn contents(board: &Board) -> Vec<CellContent> {
+        (0..board.get_width() * board.get_height()).map(|index| board.cell(index).content).collect()
+    }
 
-            let x_check = x_int + x_offset;
-            let y_check = y_int + y_offset;
+    #[test]
+    fn every_number_lies_by_one_and_stays_possible() {
+        let config = BoardConfig::new(9, 9, 30, 4, 4, GenerationMode::SafeCell).unwrap().with_liar(true).unwrap();
+        let mut lies = Vec::new();
+        for seed in 0..20 {
+            let board = create_board(&config, &mut ChaCha8Rng::seed_from_u64(seed));
+            for y in 0..9 {
+                for x in 0..9 {
+                    let Ok(CellContent::Number(value)) = board.get_content(x, y) else { continue; };
+                    let lie = board.cell(board.index(x, y).unwrap()).lie;
+                    let neighbours = board.neighbours(x, y).len() as i8;
+                    assert!(lie == 1 || lie == -1);
+                    assert!((0..=neighbours).contains(&(value + lie)), "{} shown as {}", value, value + lie);
+                    lies.push(lie);
+                }
+            }
+        }
+        assert!(lies.contains(&1) && lies.contains(&-1));
+    }
 
-            // We cannot turn -1 into a usize, so continue
-            if x_check < 0 || y_check < 0 { continue; }
-            // This is likely to return an Err for some cells, but we just ignore it as there will be no mine there
-            if let Ok(v) = board.get_value(x_check as usize, y_check as usize) {
-                if v == 9 {
-                    mine_count += 1;
+    /// A 9x9 board with moving mines, with the 3x3 area around the first click uncovered
+    fn moving_board(seed: u64) -> Board {
+        let config = BoardConfig::new(9, 9, 10, 4, 4, GenerationMode::SafeArea).unwrap()
+            .with_moving_mines(1, 3).unwrap();
+        let mut board = create_board(&config, &mut ChaCha8Rng::seed_from_u64(seed));
+        for (x, y) in board.neighbours(4, 4).into_iter().chain([(4, 4)]) {
+            board.set_state(x, y, CellState::ShowValue).unwrap();
+            board.subtract_hidden_cell();
+        }
+        board
+    }
+
+    #[test]
+    fn moved_mines_keep_every_number_right() {
+        for seed in 0..20 {
+            let mut board = moving_board(seed);
+            let before = contents(&board);
+
+            assert_eq!(move_mines(&mut board, &mut ChaCha8Rng::seed_from_u64(seed)), 3);
+            assert_eq!(board.get_mine_cell_count(), 10);
+            assert_ne!(contents(&board), before);
+            for y in 0..9 {
+                for x in 0..9 {
+                    if board.get_state(x, y) == Ok(CellState::ShowValue) {
+                        assert_eq!(board.get_content(x, y), Ok(before[y * 9 + x]));
+                    } else if !board.is_mine(x, y) {
+                        assert_eq!(board.get_content(x, y), Ok(CellContent::Number(count_surrounding_mines(x, y, &board))));
+                    }
                 }
             }
         }
     }
-    return mine_count;
-}
 
-/// Returns `true` if cell `(x1, y1) == (x2, y2)`, or
-/// is one of the 8 cells surrounding `(x2, y2)`
-fn borders_cell(x1: i32, y1: i32, x2: i32, y2: i32) -> bool {
-    return (x2 - x1).abs() <= 1 && (y2 - y1).abs() <= 1;
-}
\ No newline at end of file
+    #[test]
+    fn a_move_that_changes_an_uncovered_number_is_undone() {
+        let mut board = moving_board(0);
+        // A number that is already wrong can't be matched after the move either
+        board.set_content(4, 4, CellContent::Number(7)).unwrap();
+        let before = contents(&board);
+
+        assert_eq!(move_mines(&mut board, &mut ChaCha8Rng::seed_from_u64(0)), 0);
+        assert_eq!(contents(&board), before);
+        assert_eq!(board.get_mine_cell_count(), 10);
+    }
+}
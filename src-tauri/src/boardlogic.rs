@@ -1,118 +1,372 @@
-use serde::Serialize;
+use std::collections::VecDeque;
 
-use crate::{board::{CellState, Board, GameState}, CURRENT_BOARD};
+use rand::thread_rng;
+use serde::{Serialize, Deserialize};
+
+use crate::{board::{Action, ActionKind, CellContent, CellDetails, CellState, Board, Clue, FlagsMatch, GameState}, CURRENT_BOARD};
+use crate::boardconfig::GameMode;
+use crate::boardgenerator;
+use crate::daily;
+use crate::events;
+use crate::race;
 use crate::settings;
+use crate::spectate;
+use crate::statistics;
 
-#[derive(Serialize)]
+/// A change to a single cell. Fields that don't change are left out when sent
+#[derive(Serialize, Deserialize, Clone)]
 pub struct BoardChange {
     x: usize,
     y: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     state: Option<CellState>,
+    /// What is in the cell, if this change uncovered it and it isn't a fuzzy number
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content: Option<CellContent>,
+    /// What is shown instead of the number, if this change uncovered a fuzzy number
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    clue: Option<Clue>,
+    /// The flag level of the cell, if this change placed, raised or removed a flag
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    flags: Option<u8>,
+    /// The player that claimed the mine in this cell in a Flags match, or
+    /// that placed the flag in a co-op game
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    player: Option<usize>,
+}
+
+impl BoardChange {
+    pub fn get_position(&self) -> (usize, usize) {
+        (self.x, self.y)
+    }
+
+    pub fn get_state(&self) -> Option<CellState> {
+        self.state
+    }
+
+    pub fn get_player(&self) -> Option<usize> {
+        self.player
+    }
+}
+
+/// Something that happened to the board besides the cells the player can
+/// see changing
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type")]
+pub enum GameEvent {
+    /// Mines moved between hidden cells that don't touch an uncovered cell
+    MinesMoved { moved: usize }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct GameChange {
+    game_id: u32,
     flag_count: usize,
     hidden_cell_count: usize,
     mines_remaining: isize,
+    /// Anti-mines minus anti-flags, 0 on boards without anti-mines
+    anti_mines_remaining: isize,
     game_state: GameState,
-    changes: Vec<BoardChange>
+    /// Scores and turns, only for a Flags match
+    flags_match: Option<FlagsMatch>,
+    /// Pokes left before the mines move, only on a board with moving mines
+    pokes_until_move: Option<u32>,
+    #[serde(with = "compact_changes")]
+    changes: Vec<BoardChange>,
+    events: Vec<GameEvent>
 }
 
-#[tauri::command]
-pub fn poke(x: usize, y: usize) -> GameChange {
-    let mut changes: Vec<BoardChange> = Vec::new();
-    if let Ok(mut current_board) = CURRENT_BOARD.lock() {
-        match current_board.get_state(x, y) {
-            Ok(CellState::Hidden) => poke_hidden_cell(x, y, &mut current_board, &mut changes),
-            Ok(CellState::ShowValue) => poke_shown_cell(x, y, &mut current_board, &mut changes),
-            // Don't allow the user to poke a flagged (or question-marked) cell
-            Ok(_) => { },
-            Err(e) => { println!("ERROR (bl.p): Couldn't poke cell ({}, {}), error: {}", x, y, e); }
-        }
+impl GameChange {
+    pub fn get_game_id(&self) -> u32 {
+        self.game_id
+    }
 
-        // Don't allow the game to be won by marking too many cells as flags
-        if current_board.get_game_state() == GameState::Playing && current_board.all_mines_found() {
-            current_board.set_game_state(GameState::GameWon);
-            flag_remaining_mines(&mut current_board, &mut changes);
+    pub fn get_game_state(&self) -> GameState {
+        self.game_state
+    }
+
+    pub fn get_changes(&self) -> &[BoardChange] {
+        &self.changes
+    }
+
+    /// Applies the cell changes to `cells`, a copy of the board as the players see it
+    pub fn apply_to(&self, cells: &mut [Vec<CellDetails>]) {
+        for change in &self.changes {
+            if let Some(cell) = cells.get_mut(change.y).and_then(|row| row.get_mut(change.x)) {
+                if let Some(state) = change.state {
+                    cell.state = state;
+                }
+                cell.content = change.content.unwrap_or_default();
+                cell.clue = change.clue;
+                if let Some(flags) = change.flags {
+                    cell.flags = flags;
+                }
+            }
         }
+    }
 
-        GameChange {
-            flag_count: current_board.get_flag_count(),
-            hidden_cell_count: current_board.get_hidden_cell_count(),
-            mines_remaining: current_board.get_remaining_mines(),
-            game_state: current_board.get_game_state(),
-            changes
+    /// A copy with only the counters and game state, without the cell changes
+    pub fn summary(&self) -> GameChange {
+        GameChange { changes: Vec::new(), flags_match: self.flags_match.clone(), events: self.events.clone(), ..*self }
+    }
+
+    /// Attributes the flags placed by these changes to `player`
+    pub fn attribute_flags(&mut self, player: usize) {
+        for change in self.changes.iter_mut().filter(|c| c.state == Some(CellState::Flagged)) {
+            change.player = Some(player);
         }
+    }
 
-    } else {
-        println!("ERROR (bl.poke): Couldn't lock current board variable. No changes to the board could be made");
+    /// The changes to send back when the current board couldn't be used at all
+    fn unavailable() -> GameChange {
         GameChange {
+            game_id: 0,
             flag_count: 0,
             hidden_cell_count: 0,
             mines_remaining: 0,
+            anti_mines_remaining: 0,
             game_state: GameState::GameOver,
-            changes: Vec::new()
+            flags_match: None,
+            pokes_until_move: None,
+            changes: Vec::new(),
+            events: Vec::new()
         }
     }
 }
 
 #[tauri::command]
-pub fn mark(x: usize, y: usize) -> GameChange {
+/// Pokes cell `(x, y)` of the current board. If `game_id` is given and
+/// belongs to another game, the board is left alone
+pub fn poke(x: usize, y: usize, game_id: Option<u32>) -> GameChange {
     if let Ok(mut current_board) = CURRENT_BOARD.lock() {
-        let mut changes = Vec::new();
-
-        match (*current_board).get_state(x, y) {
-            // From Hidden, we always go to Flagged
-            Ok(CellState::Hidden) => { add_state_change(x, y, CellState::Flagged, &mut changes, &mut current_board); },
-            // From Flagged we go to Questioned if it's enabled, or back to Hidden if it's not
-            Ok(CellState::Flagged) => {
-                let next_state = if settings::is_questions_enabled() {
-                    CellState::Questioned
-                } else {
-                    CellState::Hidden
-                };
-                add_state_change(x, y, next_state, &mut changes, &mut current_board);
-            }
-            // From Questioned we always go back to Hidden
-            Ok(CellState::Questioned) => { add_state_change(x, y, CellState::Hidden, &mut changes, &mut current_board); },
-            // If we're at any other CellState, don't do anything
-            Ok(_) => {},
-            Err(e) => { println!("ERROR (bl.mark): Could not get the state of cell ({}, {}); error: {}", x, y, e) },
+        if !is_current_game(&current_board, game_id) {
+            println!("ERROR (bl.poke): Poke for game {:?} ignored, the current game is {}", game_id, current_board.get_game_id());
+            return game_change(&current_board, Vec::new());
         }
+        let game_change = poke_board(x, y, &mut current_board);
+        race::report_progress(&current_board);
+        spectate::publish(&current_board, &game_change);
+        events::publish(&current_board, &game_change);
+        game_change
+    } else {
+        println!("ERROR (bl.poke): Couldn't lock current board variable. No changes to the board could be made");
+        GameChange::unavailable()
+    }
+}
 
-        GameChange {
-            flag_count: current_board.get_flag_count(),
-            hidden_cell_count: current_board.get_hidden_cell_count(),
-            mines_remaining: current_board.get_remaining_mines(),
-            game_state: current_board.get_game_state(),
-            changes
+#[tauri::command]
+/// Cycles the mark of cell `(x, y)` of the current board. If `game_id` is
+/// given and belongs to another game, the board is left alone
+pub fn mark(x: usize, y: usize, game_id: Option<u32>) -> GameChange {
+    if let Ok(mut current_board) = CURRENT_BOARD.lock() {
+        if !is_current_game(&current_board, game_id) {
+            println!("ERROR (bl.mark): Mark for game {:?} ignored, the current game is {}", game_id, current_board.get_game_id());
+            return game_change(&current_board, Vec::new());
         }
+        let game_change = mark_board(x, y, &mut current_board);
+        race::report_progress(&current_board);
+        spectate::publish(&current_board, &game_change);
+        events::publish(&current_board, &game_change);
+        game_change
     } else {
         println!("ERROR (bl.mark): Couldn't lock current board variable. No changes to the board could be made");
-        GameChange {
-            flag_count: 0,
-            hidden_cell_count: 0,
-            mines_remaining: 0,
-            game_state: GameState::GameOver,
-            changes: Vec::new()
+        GameChange::unavailable()
+    }
+}
+
+#[tauri::command]
+/// Returns the cells of the current board as the player sees them, to catch
+/// up with a game without its changes. If `game_id` is given and belongs to
+/// another game, no cells are returned
+pub fn get_visible_cells(game_id: Option<u32>) -> Result<Vec<Vec<CellDetails>>, String> {
+    let board = CURRENT_BOARD.lock().map_err(|_| String::from("Couldn't lock the current board"))?;
+    if !is_current_game(&board, game_id) {
+        return Err(format!("Game {} is no longer the current game", game_id.unwrap_or_default()));
+    }
+    Ok(board.clone_visible_cells())
+}
+
+/// Pokes cell `(x, y)` of `board`: uncovers a hidden cell, or the cells
+/// around an uncovered one, and ends the game if that's won or lost
+pub fn poke_board(x: usize, y: usize, board: &mut Board) -> GameChange {
+    let mut changes: Vec<BoardChange> = Vec::new();
+    let was_playing = board.get_game_state() == GameState::Playing;
+    match board.get_state(x, y) {
+        // Nothing can be opened in a puzzle
+        Ok(_) if board.get_mode() == GameMode::Puzzle => { },
+        Ok(CellState::Hidden) => poke_hidden_cell(x, y, board, &mut changes),
+        // In a Flags match, every turn is a single poke
+        Ok(CellState::ShowValue) if board.get_mode() == GameMode::Flags => { },
+        // The numbers of a liar board can't be checked against the flags around them
+        Ok(CellState::ShowValue) if board.is_liar() => { },
+        Ok(CellState::ShowValue) => poke_shown_cell(x, y, board, &mut changes),
+        // Don't allow the user to poke a flagged (or question-marked) cell
+        Ok(_) => { },
+        Err(e) => { println!("ERROR (bl.p): Couldn't poke cell ({}, {}), error: {}", x, y, e); }
+    }
+
+    // Don't allow the game to be won by marking too many cells as flags.
+    // A Flags match is only won by claiming mines, and a puzzle by submitting it
+    if board.get_mode() == GameMode::Classic && board.get_game_state() == GameState::Playing && board.all_mines_found() {
+        board.set_game_state(GameState::GameWon);
+        flag_remaining_mines(board, &mut changes);
+    }
+
+    if was_playing && !changes.is_empty() {
+        record_action(ActionKind::Poke, x, y, board, &changes);
+    }
+
+    let mut events = Vec::new();
+    if was_playing && board.get_game_state() == GameState::Playing && !changes.is_empty() && board.count_poke_until_move() {
+        let moved = boardgenerator::move_mines(board, &mut thread_rng());
+        events.push(GameEvent::MinesMoved { moved });
+    }
+
+    // A Flags match has no single player to keep statistics for, and a
+    // puzzle doesn't end by poking
+    if was_playing && board.get_game_state() != GameState::Playing && board.get_mode() == GameMode::Classic
+            && board.counts_in_statistics() {
+        let won = board.get_game_state() == GameState::GameWon;
+        statistics::record_game(board.get_preset(), won, board.get_elapsed_time());
+        if let Some(date) = board.get_daily_date() {
+            daily::record_result(date, board.get_preset(), won, board.get_elapsed_time());
         }
     }
+
+    GameChange { events, ..game_change(board, changes) }
+}
+
+/// Cycles the mark of cell `(x, y)` of `board` between flagged (with every
+/// flag level on a board with multi-mine cells), anti-flagged (on a board
+/// with anti-mines), question-marked (if enabled) and hidden
+pub fn mark_board(x: usize, y: usize, board: &mut Board) -> GameChange {
+    let mut changes = Vec::new();
+
+    match board.get_state(x, y) {
+        // In a Flags match, flags are only placed by claiming a mine
+        Ok(_) if board.get_mode() == GameMode::Flags => {},
+        // From Hidden, we always go to Flagged
+        Ok(CellState::Hidden) => { add_state_change(x, y, CellState::Flagged, &mut changes, board); },
+        // A flag can be raised up to the most mines a cell can hold
+        Ok(CellState::Flagged) if board.get_flags(x, y).is_ok_and(|flags| flags < board.get_max_cell_mines()) => {
+            raise_flag(x, y, &mut changes, board);
+        }
+        // From Flagged we go to AntiFlagged if the board has anti-mines
+        Ok(CellState::Flagged) if board.get_anti_mine_total() > 0 => {
+            add_state_change(x, y, CellState::AntiFlagged, &mut changes, board);
+        }
+        // From (Anti)Flagged we go to Questioned if it's enabled, or back to Hidden if it's not
+        Ok(CellState::Flagged) | Ok(CellState::AntiFlagged) => {
+            let next_state = if settings::is_questions_enabled() {
+                CellState::Questioned
+            } else {
+                CellState::Hidden
+            };
+            add_state_change(x, y, next_state, &mut changes, board);
+        }
+        // From Questioned we always go back to Hidden
+        Ok(CellState::Questioned) => { add_state_change(x, y, CellState::Hidden, &mut changes, board); },
+        // If we're at any other CellState, don't do anything
+        Ok(_) => {},
+        Err(e) => { println!("ERROR (bl.mark): Could not get the state of cell ({}, {}); error: {}", x, y, e) },
+    }
+
+    if board.get_game_state() == GameState::Playing && !changes.is_empty() {
+        record_action(ActionKind::Mark, x, y, board, &changes);
+    }
+
+    game_change(board, changes)
+}
+
+/// Checks the flags of a puzzle on `board`. The puzzle is solved if exactly
+/// the mines are flagged. Either way the game ends, showing missed mines and
+/// wrong flags
+pub fn submit_puzzle_board(board: &mut Board) -> GameChange {
+    let mut changes = Vec::new();
+    if board.get_mode() == GameMode::Puzzle && board.get_game_state() == GameState::Playing {
+        let mut solved = board.get_flag_count() == board.get_mine_cell_count();
+        for x in 0..board.get_width() {
+            for y in 0..board.get_height() {
+                if board.get_state(x, y) == Ok(CellState::Flagged) && !board.is_mine(x, y) {
+                    solved = false;
+                }
+            }
+        }
+        board.set_game_state(if solved { GameState::GameWon } else { GameState::GameOver });
+        show_all_mines(board, &mut changes);
+    }
+    game_change(board, changes)
+}
+
+/// Adds an action to the log of `board`, with the cells its `changes` uncovered
+fn record_action(kind: ActionKind, x: usize, y: usize, board: &mut Board, changes: &[BoardChange]) {
+    let uncovered = changes.iter()
+        .filter(|change| match change.state {
+            Some(CellState::ShowMineExploded) => true,
+            // Mines are only shown with their value when the game ends
+            Some(CellState::ShowValue) => change.clue.is_some() || matches!(change.content, Some(CellContent::Number(_))),
+            _ => false
+        })
+        .map(|change| (change.x, change.y))
+        .collect();
+    board.record_action(Action { kind, x, y, elapsed: board.get_elapsed_time(), uncovered });
+}
+
+/// True if `game_id` is not given, or is the ID of the game on `board`
+fn is_current_game(board: &Board, game_id: Option<u32>) -> bool {
+    game_id.is_none_or(|id| id == board.get_game_id())
 }
 
+fn game_change(board: &Board, changes: Vec<BoardChange>) -> GameChange {
+    GameChange {
+        game_id: board.get_game_id(),
+        flag_count: board.get_flag_count(),
+        hidden_cell_count: board.get_hidden_cell_count(),
+        mines_remaining: board.get_remaining_mines(),
+        anti_mines_remaining: board.get_remaining_anti_mines(),
+        game_state: board.get_game_state(),
+        flags_match: board.get_flags_match().cloned(),
+        pokes_until_move: board.get_pokes_until_move(),
+        changes,
+        events: Vec::new()
+    }
+}
+
+/// Sets `(x, y)` to `state` and records the change. What is in a cell is only
+/// sent once it's uncovered, which for a mine only happens when the game ends
 fn add_state_change(x: usize, y: usize, state: CellState, changes: &mut Vec<BoardChange>, board: &mut Board) {
     if let Ok(old_state) = board.get_state(x, y) {
         if let Ok(()) = board.set_state(x, y, state) {
-            changes.push(BoardChange { x, y, state: Some(state) });
+            let (content, clue) = match (state, board.get_clue(x, y)) {
+                (CellState::ShowValue, Some(clue)) => (None, Some(clue)),
+                (CellState::ShowValue | CellState::ShowMineExploded, _) => (board.get_shown_content(x, y).ok(), None),
+                _ => (None, None)
+            };
+            let mut flags = None;
+            if old_state == CellState::Flagged && state != CellState::Flagged {
+                board.subtract_flag();
+                board.add_hidden_cell();
+                flags = Some(0);
+            } else if old_state == CellState::AntiFlagged && state != CellState::AntiFlagged {
+                board.subtract_anti_flag();
+                board.add_hidden_cell();
+            }
             if old_state == CellState::Hidden && state == CellState::ShowValue {
                 board.subtract_hidden_cell();
             } else if old_state != CellState::Flagged && state == CellState::Flagged {
                 board.add_flag();
                 board.subtract_hidden_cell();
-            } else if old_state == CellState::Flagged && state != CellState::Flagged {
-                board.subtract_flag();
-                board.add_hidden_cell();
+                flags = Some(1);
+            } else if old_state != CellState::AntiFlagged && state == CellState::AntiFlagged {
+                board.add_anti_flag();
+                board.subtract_hidden_cell();
             }
+            if let Some(flags) = flags {
+                if let Err(e) = board.set_flags(x, y, flags) {
+                    println!("ERROR (bl.asc): Couldn't set the flags of cell ({}, {}), error: {}", x, y, e);
+                }
+            }
+            changes.push(BoardChange { x, y, state: Some(state), content, clue, flags, player: None });
         } else {
             println!("ERROR (bl.asc): Couldn't set cell ({}, {}) to state {:?}", x, y, state);
         }
@@ -121,16 +375,22 @@ fn add_state_change(x: usize, y: usize, state: CellState, changes: &mut Vec<Boar
 
 fn poke_hidden_cell(x: usize, y: usize, board: &mut Board, changes: &mut Vec<BoardChange>) {
     poke_single_cell(x, y, board, changes);
+
+    // Poking a cell without a mine ends the turn in a Flags match
+    if board.get_game_state() == GameState::Playing && !board.is_mine(x, y) {
+        if let Some(flags_match) = board.get_flags_match_mut() {
+            flags_match.next_turn();
+        }
+    }
 }
 
 fn poke_shown_cell(x: usize, y: usize, board: &mut Board, changes: &mut Vec<BoardChange>) {
     if settings::is_quick_uncover_enabled() {
         if settings::is_protected_uncover_enabled() {
             let flags = count_flags(x, y, &board);
-            if let Ok(v) = board.get_value(x, y) {
-                if v == flags {
-                    poke_around_cell(x, y, board, changes);
-                }
+            // A fuzzy number never tells exactly how many flags it needs
+            if board.get_clue(x, y).is_none() && board.get_content(x, y) == Ok(CellContent::Number(flags)) {
+                poke_around_cell(x, y, board, changes);
             }
         } else {
             poke_around_cell(x, y, board, changes);
@@ -142,84 +402,119 @@ fn show_all_mines(board: &mut Board, changes: &mut Vec<BoardChange>) {
     for x in 0..board.get_width() {
         for y in 0..board.get_height() {
             if let Ok(state) = board.get_state(x, y) {
-                // Unveil all mines that are still hidden or question-marked
+                // Unveil all mines and anti-mines that are still hidden or question-marked
                 if state == CellState::Hidden || state == CellState::Questioned {
-                    if let Ok(v) = board.get_value(x, y) {
-                        if v == 9 {
-                            add_state_change(x, y, CellState::ShowValue, changes, board);
-                        }
+                    if board.get_content(x, y).is_ok_and(|content| !content.is_safe()) {
+                        add_state_change(x, y, CellState::ShowValue, changes, board);
+                    }
+                } else if state == CellState::AntiFlagged {
+                    if board.get_content(x, y).is_ok_and(|content| !content.is_anti_mine()) {
+                        add_state_change(x, y, CellState::ShowInvalidMine, changes, board);
                     }
                 } else if state == CellState::Flagged {
-                    if let Ok(v) = board.get_value(x, y) {
-                        if v != 9 {
+                    // A flag is wrong on a cell without mines, or with a different number of them
+                    if let (Ok(content), Ok(flags)) = (board.get_content(x, y), board.get_flags(x, y)) {
+                        if content.mine_count() != flags {
                             add_state_change(x, y, CellState::ShowInvalidMine, changes, board);
                         }
                     }
-                } 
+                }
             }
         }
     }
 }
 
 fn poke_around_cell(x: usize, y: usize, board: &mut Board, changes: &mut Vec<BoardChange>) {
-    let x_int = x as i32;
-    let y_int = y as i32;
-    for x_offset in [-1, 0, 1] {
-        for y_offset in [-1, 0, 1] {
-            // Don't poke the cell itself
-            if x_offset == 0 && y_offset == 0 { continue; }
+    for (x_poke, y_poke) in board.neighbours(x, y) {
+        poke_single_cell(x_poke, y_poke, board, changes)
+    }
+}
 
-            let x_poke = x_int + x_offset;
-            let y_poke = y_int + y_offset;
+/// Uncovers `(x, y)` if it's hidden, and the opening around it if it has no
+/// mines around it. The opening is filled from a queue instead of by
+/// recursion, so it can't run out of stack on a large board
+fn poke_single_cell(x: usize, y: usize, board: &mut Board, changes: &mut Vec<BoardChange>) {
+    let Some(start) = board.index(x, y) else {
+        println!("ERROR (bl.psc): Couldn't poke cell ({}, {}), it isn't on the board", x, y);
+        return;
+    };
+    let mut queue = VecDeque::from([start]);
+    while let Some(index) = queue.pop_front() {
+        let cell = *board.cell(index);
+        if cell.state != CellState::Hidden { continue; }
 
-            // We cannot turn -1 into a usize, so continue
-            if x_poke < 0 || y_poke < 0 { continue; }
-            // This is likely to return an Err for some cells, but we just ignore it as we don't need to poke there
-            poke_single_cell(x_poke as usize, y_poke as usize, board, changes)
+        let (x, y) = board.position(index);
+        match cell.content {
+            CellContent::Mines(_) if board.get_mode() == GameMode::Flags => claim_mine(x, y, board, changes),
+            CellContent::Mines(_) | CellContent::AntiMine => {
+                add_state_change(x, y, CellState::ShowMineExploded, changes, board);
+                board.set_game_state(GameState::GameOver);
+                show_all_mines(board, changes);
+            },
+            // A 0 can also be mines and anti-mines cancelling out, so only
+            // open around cells that have neither around them. Opening
+            // around a cell on a liar board would give its lie away
+            CellContent::Number(_) if !board.is_liar() && is_safe_around(index, board) => {
+                add_state_change(x, y, CellState::ShowValue, changes, board);
+                queue.extend(board.neighbour_indices(index));
+            },
+            CellContent::Number(_) => add_state_change(x, y, CellState::ShowValue, changes, board)
         }
     }
 }
 
-fn poke_single_cell(x: usize, y: usize, board: &mut Board, changes: &mut Vec<BoardChange>) {
-    if let Ok(state) = board.get_state(x, y) {
-        if state == CellState::Hidden {
-            match board.get_value(x, y) {
-                Ok(9) => {
-                    add_state_change(x, y, CellState::ShowMineExploded, changes, board);
-                    board.set_game_state(GameState::GameOver);
-                    show_all_mines(board, changes);
-                },
-                Ok(0) => {
-                    add_state_change(x, y, CellState::ShowValue, changes, board);
-                    poke_around_cell(x, y, board, changes);
-                },
-                Ok(_) => add_state_change(x, y, CellState::ShowValue, changes, board),
-                Err(e) => println!("ERROR (bl.psc): Couldn't poke cell ({}, {}), error: {}", x, y, e)
-            }
-        }
+/// Raises the flag on `(x, y)` by one level, claiming one more mine in the cell
+fn raise_flag(x: usize, y: usize, changes: &mut Vec<BoardChange>, board: &mut Board) {
+    if let Ok(flags) = board.get_flags(x, y) {
+        set_flag_level(x, y, flags + 1, changes, board);
     }
 }
 
-fn count_flags(x: usize, y: usize, board: &Board) -> u8 {
-    let mut flag_count = 0u8;
-    let x_int = x as i32;
-    let y_int = y as i32;
-    for x_offset in [-1, 0, 1] {
-        for y_offset in [-1, 0, 1] {
-            // Don't check the cell itself
-            if x_offset == 0 && y_offset == 0 { continue; }
+/// Sets the level of the flag on `(x, y)` to `flags`
+fn set_flag_level(x: usize, y: usize, flags: u8, changes: &mut Vec<BoardChange>, board: &mut Board) {
+    match board.set_flags(x, y, flags) {
+        Ok(()) => changes.push(BoardChange { x, y, state: Some(CellState::Flagged), content: None, clue: None,
+            flags: Some(flags), player: None }),
+        Err(e) => println!("ERROR (bl.sfl): Couldn't set the flags of cell ({}, {}), error: {}", x, y, e)
+    }
+}
 
-            let x_check = x_int + x_offset;
-            let y_check = y_int + y_offset;
+/// Flags the mine at `(x, y)` for the player whose turn it is in a Flags
+/// match. The match ends once a player has claimed more than half of the
+/// mines, or when all mines are claimed without a winner
+fn claim_mine(x: usize, y: usize, board: &mut Board, changes: &mut Vec<BoardChange>) {
+    let total_mines = board.get_mine_total();
+    let Some(flags_match) = board.get_flags_match_mut() else { return; };
+    let player = flags_match.get_current_player();
+    let won = flags_match.claim_mine(total_mines);
 
-            // We cannot turn -1 into a usize, so continue
-            if x_check < 0 || y_check < 0 { continue; }
-            // This is likely to return an Err for some cells, but we just ignore it as there will be no mine there
-            if let Ok(state) = board.get_state(x_check as usize, y_check as usize) {
-                if state == CellState::Flagged {
-                    flag_count += 1;
-                }
-            }
+    add_state_change(x, y, CellState::Flagged, changes, board);
+    if let Some(change) = changes.last_mut() {
+        change.player = Some(player);
+    }
+
+    if won || board.get_flag_count() == total_mines {
+        board.set_game_state(GameState::GameWon);
+        show_all_mines(board, changes);
+    }
+}
+
+/// True if none of the cells around the cell at `index` hold a mine or an anti-mine
+fn is_safe_around(index: usize, board: &Board) -> bool {
+    board.neighbour_indices(index).all(|neighbour| board.cell(neighbour).content.is_safe())
+}
+
+/// Counts the flags around `(x, y)` the way numbers count mines: every flag
+/// counts as its level, and every anti-flag takes 1 off
+fn count_flags(x: usize, y: usize, board: &Board) -> i8 {
+    let Some(index) = board.index(x, y) else { return 0; };
+    let mut flag_count = 0i8;
+    for neighbour in board.neighbour_indices(index) {
+        let cell = board.cell(neighbour);
+        match cell.state {
+            CellState::Flagged => flag_count += cell.flags as i8,
+            CellState::AntiFlagged => flag_count -= 1,
+            _ => {}
         }
     }
     return flag_count;
@@ -230,10 +525,15 @@ fn flag_remaining_mines(board: &mut Board, changes: &mut Vec<BoardChange>) {
         for y in 0..board.get_height() {
             if let Ok(state) = board.get_state(x, y) {
                 if state == CellState::Hidden {
-                    if let Ok(v) = board.get_value(x, y) {
-                        if v == 9 {
+                    match board.get_content(x, y) {
+                        Ok(CellContent::Mines(count)) => {
                             add_state_change(x, y, CellState::Flagged, changes, board);
-                        }
+                            if count > 1 {
+                                set_flag_level(x, y, count, changes, board);
+                            }
+                        },
+                        Ok(CellContent::AntiMine) => add_state_change(x, y, CellState::AntiFlagged, changes, board),
+                        _ => {}
                     }
                 }
             }
@@ -241,3 +541,173 @@ fn flag_remaining_mines(board: &mut Board, changes: &mut Vec<BoardChange>) {
     }
 }
 
+/// The cell changes of a `GameChange` in the form they are sent in. A large
+/// opening uncovers many cells next to each other, so uncovered numbers are
+/// sent as runs of numbers along a row instead of as a change per cell
+mod compact_changes {
+    use serde::{Serialize, Deserialize, Serializer, Deserializer};
+
+    use crate::board::{CellContent, CellState};
+    use super::BoardChange;
+
+    #[derive(Serialize, Deserialize)]
+    struct CompactChanges {
+        /// Uncovered cells that show a plain number. These are applied first
+        runs: Vec<NumberRun>,
+        /// Every other change, in order
+        other: Vec<BoardChange>
+    }
+
+    /// Uncovered cells next to each other in a row
+    #[derive(Serialize, Deserialize)]
+    struct NumberRun {
+        x: usize,
+        y: usize,
+        /// The numbers of the cells from `(x, y)` to the right
+        numbers: Vec<i8>
+    }
+
+    pub fn serialize<S: Serializer>(changes: &[BoardChange], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut numbers = Vec::new();
+        let mut other = Vec::new();
+        for change in changes {
+            match change {
+                BoardChange { x, y, state: Some(CellState::ShowValue), content: Some(CellContent::Number(number)),
+                        clue: None, flags: None, player: None } => numbers.push((*y, *x, *number)),
+                _ => other.push(change.clone())
+            }
+        }
+
+        numbers.sort_unstable();
+        let mut runs: Vec<NumberRun> = Vec::new();
+        for (y, x, number) in numbers {
+            match runs.last_mut() {
+                Some(run) if run.y == y && run.x + run.numbers.len() == x => run.numbers.push(number),
+                _ => runs.push(NumberRun { x, y, numbers: vec![number] })
+            }
+        }
+        CompactChanges { runs, other }.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<BoardChange>, D::Error> {
+        let CompactChanges { runs, other } = CompactChanges::deserialize(deserializer)?;
+        let mut changes: Vec<BoardChange> = runs.into_iter()
+            .flat_map(|NumberRun { x, y, numbers }| numbers.into_iter().enumerate()
+                .map(move |(offset, number)| BoardChange { x: x + offset, y, state: Some(CellState::ShowValue),
+                    content: Some(CellContent::Number(number)), clue: None, flags: None, player: None }))
+            .collect();
+        changes.extend(other);
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boardconfig::BoardConfig;
+    use crate::settings::GenerationMode;
+
+    /// A board from `config` with `hazards` in their cells and the right number in every other cell
+    fn board_with(config: BoardConfig, hazards: &[(usize, usize, CellContent)]) -> Board {
+        let mut board = Board::new(&config);
+        for (x, y, content) in hazards {
+            board.set_content(*x, *y, *content).unwrap();
+        }
+        for index in 0..board.get_width() * board.get_height() {
+            if let CellContent::Number(_) = board.cell(index).content {
+                let value = board.neighbour_indices(index).map(|neighbour| board.cell(neighbour).content.weight()).sum();
+                board.set_content_at(index, CellContent::Number(value)).unwrap();
+            }
+        }
+        board
+    }
+
+    #[test]
+    fn a_zero_from_a_mine_and_an_anti_mine_doesnt_open_around_it() {
+        let config = BoardConfig::new(5, 4, 1, 4, 3, GenerationMode::SafeCell).unwrap().with_anti_mines(1).unwrap();
+        let mut board = board_with(config, &[(0, 0, CellContent::Mines(1)), (1, 0, CellContent::AntiMine)]);
+        assert_eq!(board.get_content(0, 1), Ok(CellContent::Number(0)));
+
+        // The mine and the anti-mine cancel out, but the cells around are still dangerous
+        let change = poke_board(0, 1, &mut board);
+        assert_eq!(change.changes.len(), 1);
+        assert_eq!(board.get_game_state(), GameState::Playing);
+
+        // A real 0 opens around it, up to the cells next to the mine and the
+        // anti-mine. That uncovers every other cell, so the game is won
+        poke_board(4, 3, &mut board);
+        assert_eq!(board.get_game_state(), GameState::GameWon);
+        assert_eq!(board.get_shown_content(1, 1), Ok(CellContent::Number(0)));
+        assert_eq!(board.get_shown_content(2, 1), Ok(CellContent::Number(-1)));
+        assert_eq!(board.get_state(0, 0), Ok(CellState::Flagged));
+        assert_eq!(board.get_state(1, 0), Ok(CellState::AntiFlagged));
+    }
+
+    #[test]
+    fn flags_go_up_to_the_most_mines_a_cell_holds_and_count_as_their_level() {
+        let config = BoardConfig::new(4, 4, 2, 3, 3, GenerationMode::SafeCell).unwrap().with_max_cell_mines(3).unwrap();
+        let mut board = board_with(config, &[(0, 0, CellContent::Mines(2))]);
+
+        for level in 1..=3 {
+            mark_board(0, 0, &mut board);
+            assert_eq!(board.get_state(0, 0), Ok(CellState::Flagged));
+            assert_eq!(board.get_flags(0, 0), Ok(level));
+            assert_eq!(board.get_flag_count(), 1);
+        }
+        // Past the highest level the flag comes off (or becomes a question mark)
+        mark_board(0, 0, &mut board);
+        assert_ne!(board.get_state(0, 0), Ok(CellState::Flagged));
+        assert_eq!(board.get_flags(0, 0), Ok(0));
+        assert_eq!(board.get_flag_count(), 0);
+        if board.get_state(0, 0) == Ok(CellState::Questioned) {
+            mark_board(0, 0, &mut board);
+        }
+
+        // A 2 is satisfied by one flag of level 2, so poking it opens the cells around it
+        poke_board(1, 1, &mut board);
+        assert_eq!(board.get_shown_content(1, 1), Ok(CellContent::Number(2)));
+        mark_board(0, 0, &mut board);
+        poke_board(1, 1, &mut board);
+        assert_eq!(board.get_state(0, 1), Ok(CellState::Hidden));
+        mark_board(0, 0, &mut board);
+        poke_board(1, 1, &mut board);
+        assert_eq!(board.get_state(0, 1), Ok(CellState::ShowValue));
+        // The cells around the 2 include zeros, which open the rest of the board
+        assert_eq!(board.get_game_state(), GameState::GameWon);
+    }
+
+    fn number(x: usize, y: usize, number: i8) -> BoardChange {
+        BoardChange { x, y, state: Some(CellState::ShowValue), content: Some(CellContent::Number(number)),
+            clue: None, flags: None, player: None }
+    }
+
+    #[test]
+    fn compact_changes_group_numbers_in_runs() {
+        let flag = BoardChange { x: 3, y: 3, state: Some(CellState::Flagged), content: None, clue: None,
+            flags: Some(1), player: None };
+        let changes = vec![number(2, 1, 0), number(3, 1, 1), number(4, 1, 2), number(0, 2, 3), flag];
+
+        let value = compact_changes::serialize(&changes, serde_json::value::Serializer).unwrap();
+        assert_eq!(value["runs"], serde_json::json!([
+            { "x": 2, "y": 1, "numbers": [0, 1, 2] },
+            { "x": 0, "y": 2, "numbers": [3] }
+        ]));
+        assert_eq!(value["other"].as_array().unwrap().len(), 1);
+
+        let restored = compact_changes::deserialize(value).unwrap();
+        assert_eq!(serde_json::to_value(&restored).unwrap(), serde_json::to_value(&changes).unwrap());
+    }
+
+    #[test]
+    fn compact_changes_apply_runs_before_other_changes() {
+        // The mine shown on a loss isn't a plain number, so it's sent after the runs
+        let mine = BoardChange { x: 0, y: 0, state: Some(CellState::ShowMineExploded),
+            content: Some(CellContent::Mines(1)), clue: None, flags: None, player: None };
+        let changes = vec![mine, number(1, 0, 1)];
+
+        let value = compact_changes::serialize(&changes, serde_json::value::Serializer).unwrap();
+        let restored = compact_changes::deserialize(value).unwrap();
+        let positions: Vec<(usize, usize)> = restored.iter().map(BoardChange::get_position).collect();
+        assert_eq!(positions, vec![(1, 0), (0, 0)]);
+    }
+}
@@ -0,0 +1,113 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::board::{Board, CellState};
+use crate::boardlogic::{self, GameChange};
+use crate::events;
+use crate::hint::{self, Hint, HintKind};
+use crate::race;
+use crate::spectate;
+use crate::CURRENT_BOARD;
+
+/// Time between moves when auto-playing, if none is given
+const DEFAULT_DELAY_MS: u64 = 250;
+
+/// Longest time between moves when auto-playing
+const MAX_DELAY_MS: u64 = 10_000;
+
+/// Whether the auto-player that was started last should keep playing
+static AUTO_PLAY: Mutex<Option<Arc<AtomicBool>>> = Mutex::new(None);
+
+#[tauri::command]
+/// Makes the next move on the current board. If `game_id` is given and
+/// belongs to another game, the board is left alone
+pub fn bot_step(game_id: Option<u32>) -> Result<GameChange, String> {
+    let mut board = CURRENT_BOARD.lock().map_err(|_| String::from("Couldn't lock the current board"))?;
+    if game_id.is_some_and(|id| id != board.get_game_id()) {
+        return Err(format!("Game {} is no longer the current game", game_id.unwrap_or_default()));
+    }
+    let (_, change) = step(&mut board)?;
+    publish(&board, &change);
+    Ok(change)
+}
+
+#[tauri::command]
+/// Plays the current game until it ends or `stop_auto_play` is called,
+/// with `delay_ms` between moves. Every move is sent as `board-changed`
+pub fn start_auto_play(game_id: Option<u32>, delay_ms: Option<u64>) -> Result<(), String> {
+    let game_id = {
+        let board = CURRENT_BOARD.lock().map_err(|_| String::from("Couldn't lock the current board"))?;
+        if game_id.is_some_and(|id| id != board.get_game_id()) {
+            return Err(format!("Game {} is no longer the current game", game_id.unwrap_or_default()));
+        }
+        hint::hint_for(&board)?;
+        board.get_game_id()
+    };
+
+    let mut auto_play = AUTO_PLAY.lock().map_err(|_| String::from("Couldn't lock the auto-player"))?;
+    if let Some(running) = auto_play.take() {
+        running.store(false, Ordering::Relaxed);
+    }
+    let running = Arc::new(AtomicBool::new(true));
+    *auto_play = Some(running.clone());
+
+    let delay = Duration::from_millis(delay_ms.unwrap_or(DEFAULT_DELAY_MS).min(MAX_DELAY_MS));
+    thread::spawn(move || auto_play_game(game_id, delay, running));
+    Ok(())
+}
+
+#[tauri::command]
+/// Stops the auto-player after the move it is making
+pub fn stop_auto_play() {
+    if let Ok(mut auto_play) = AUTO_PLAY.lock() {
+        if let Some(running) = auto_play.take() {
+            running.store(false, Ordering::Relaxed);
+        }
+    } else {
+        println!("ERROR (bot.sap): Couldn't lock the auto-player, it wasn't stopped");
+    }
+}
+
+/// Makes the next move on `board` from only what the player can see: pokes
+/// a proven safe cell, flags a proven mine, or else pokes the cell that is
+/// least likely to be a mine. Works on any board, not just the current one.
+/// Returns the hint that was followed along with the change
+pub fn step(board: &mut Board) -> Result<(Hint, GameChange), String> {
+    let hint = hint::hint_for(board)?;
+    board.exclude_from_statistics();
+    let change = match (board.get_state(hint.x, hint.y)?, hint.kind) {
+        // A question mark blocks pokes and flags alike, so clear it first
+        (CellState::Questioned, _) => boardlogic::mark_board(hint.x, hint.y, board),
+        (_, HintKind::Mine) => boardlogic::mark_board(hint.x, hint.y, board),
+        (_, HintKind::Safe) | (_, HintKind::Guess) => boardlogic::poke_board(hint.x, hint.y, board)
+    };
+    Ok((hint, change))
+}
+
+/// Thread that makes a move on game `game_id` every `delay` for as long as
+/// it is the current game, it is being played, and `running` is set
+fn auto_play_game(game_id: u32, delay: Duration, running: Arc<AtomicBool>) {
+    while running.load(Ordering::Relaxed) {
+        thread::sleep(delay);
+        let Ok(mut board) = CURRENT_BOARD.lock() else {
+            println!("ERROR (bot.apg): Couldn't lock the current board, auto-play stopped");
+            break;
+        };
+        if board.get_game_id() != game_id || !running.load(Ordering::Relaxed) {
+            break;
+        }
+        match step(&mut board) {
+            Ok((_, change)) => publish(&board, &change),
+            Err(_) => break
+        }
+    }
+    running.store(false, Ordering::Relaxed);
+}
+
+fn publish(board: &Board, change: &GameChange) {
+    race::report_progress(board);
+    spectate::publish(board, change);
+    events::publish(board, change);
+}
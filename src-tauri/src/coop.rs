@@ -0,0 +1,683 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use rand::thread_rng;
+use serde::{Serialize, Deserialize};
+
+use crate::board::{Board, CellDetails, CellState};
+use crate::boardconfig::{self, BoardConfig, Topology};
+use crate::boardgenerator;
+use crate::boardlogic::{self, GameChange};
+use crate::events;
+use crate::network::{self, Connection};
+use crate::settings;
+use crate::spectate;
+use crate::CURRENT_BOARD;
+
+/// The co-op game this instance is hosting or has joined, if any.
+/// When both are needed, lock `CURRENT_BOARD` first and this one second
+static COOP: Mutex<Option<CoopSession>> = Mutex::new(None);
+
+/// How often the host applies the actions it gathered from the players
+const TICK: Duration = Duration::from_millis(50);
+
+/// Something a player does in a co-op game
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(tag = "type")]
+pub enum CoopAction {
+    Poke { x: usize, y: usize, game_id: Option<u32> },
+    Mark { x: usize, y: usize, game_id: Option<u32> },
+    /// Where the player's mouse is
+    Cursor { x: usize, y: usize },
+    /// Draws the attention of the other players to a cell
+    Ping { x: usize, y: usize }
+}
+
+/// An action and the ID of the player that performed it
+type PlayerAction = (u32, CoopAction);
+
+impl CoopAction {
+    /// The cell a poke or mark changes, with its priority when several
+    /// actions on that cell conflict (lower goes first)
+    fn target(&self) -> Option<((usize, usize), u8)> {
+        match *self {
+            CoopAction::Poke { x, y, .. } => Some(((x, y), 0)),
+            CoopAction::Mark { x, y, .. } => Some(((x, y), 1)),
+            CoopAction::Cursor { .. } | CoopAction::Ping { .. } => None
+        }
+    }
+}
+
+/// Something that happened in a co-op game, as sent to every player
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type")]
+pub enum CoopUpdate {
+    /// The host set up a new game. Its board is generated by the first poke
+    NewGame { game: CoopGame },
+    /// The result of a poke or mark by `player_id`
+    Changes { player_id: u32, change: GameChange },
+    Cursor { player_id: u32, x: usize, y: usize },
+    Ping { player_id: u32, x: usize, y: usize },
+    /// A poke or mark that lost against another action on the same cell,
+    /// or that was meant for another game
+    Rejected { player_id: u32, x: usize, y: usize }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SequencedUpdate {
+    seq: u64,
+    update: CoopUpdate
+}
+
+/// The settings of the board everyone plays on
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct CoopGame {
+    width: u32,
+    height: u32,
+    mines: u32,
+    #[serde(default)]
+    topology: Topology
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CoopPlayer {
+    player_id: u32,
+    name: String
+}
+
+/// What a player needs to show a game that's already going on. The cells
+/// only contain what the players can see, never hidden values
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CoopSnapshot {
+    /// Sequence number of the last update that is included
+    seq: u64,
+    game: Option<CoopGame>,
+    /// ID of the board, once the first poke generated it
+    game_id: Option<u32>,
+    cells: Vec<Vec<CellDetails>>,
+    /// `(x, y, player_id)` of every flag and who placed it
+    flag_owners: Vec<(usize, usize, u32)>,
+    /// `(player_id, x, y)` of every known cursor
+    cursors: Vec<(u32, usize, usize)>
+}
+
+/// The messages sent between the host and the other players, one JSON object per line
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum CoopMessage {
+    /// Sent by a player right after connecting
+    Join { name: String },
+    /// Sent by the host to a player that joined
+    Welcome { player_id: u32, snapshot: CoopSnapshot },
+    /// Sent by the host whenever a player joins or leaves
+    Players { players: Vec<CoopPlayer> },
+    /// Sent by a player to the host
+    Action { action: CoopAction },
+    /// Sent by the host to all players
+    Update { update: SequencedUpdate },
+    /// Sent by the host to all players for cursors and pings, which are
+    /// only shown live and not kept with the updates
+    Presence { update: CoopUpdate }
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+pub enum CoopRole {
+    Host,
+    Guest
+}
+
+#[derive(Serialize)]
+pub struct CoopStatus {
+    role: CoopRole,
+    player_id: u32,
+    players: Vec<CoopPlayer>
+}
+
+struct CoopSession {
+    role: CoopRole,
+    player_id: u32,
+    players: Vec<CoopPlayer>,
+    /// For the host, one connection per guest. For a guest, only the
+    /// connection to the host (with the host's player ID, 0)
+    connections: Vec<(u32, Connection)>,
+    /// Host only: the actions received since the last tick, in order of arrival
+    pending: Vec<PlayerAction>,
+    game: Option<CoopGame>,
+    game_id: Option<u32>,
+    flag_owners: BTreeMap<(usize, usize), u32>,
+    cursors: BTreeMap<u32, (usize, usize)>,
+    /// All updates of the current game except cursors and pings, oldest first
+    updates: Vec<SequencedUpdate>,
+    next_seq: u64,
+    /// Guest only: the snapshot received when joining or when a new game started
+    snapshot: Option<CoopSnapshot>,
+    next_player_id: u32,
+    /// Cleared when the session ends, which stops its threads
+    running: Arc<AtomicBool>
+}
+
+impl CoopSession {
+    fn new(role: CoopRole, name: String) -> CoopSession {
+        CoopSession {
+            role,
+            player_id: 0,
+            players: vec![CoopPlayer { player_id: 0, name }],
+            connections: Vec::new(),
+            pending: Vec::new(),
+            game: None,
+            game_id: None,
+            flag_owners: BTreeMap::new(),
+            cursors: BTreeMap::new(),
+            updates: Vec::new(),
+            next_seq: 1,
+            snapshot: None,
+            next_player_id: 1,
+            running: Arc::new(AtomicBool::new(true))
+        }
+    }
+
+    /// Sends `message` to every connection of this session
+    fn broadcast(&self, message: &CoopMessage) {
+        for (player_id, connection) in &self.connections {
+            if let Err(e) = connection.send(message) {
+                println!("ERROR (c.b): Couldn't send a message to player {}: {}", player_id, e);
+            }
+        }
+    }
+
+    fn broadcast_players(&self) {
+        self.broadcast(&CoopMessage::Players { players: self.players.clone() });
+    }
+
+    /// Guest only: sends the action of the local player to the host
+    fn send_to_host(&self, action: CoopAction) -> Result<(), String> {
+        let (_, connection) = self.connections.first().ok_or_else(|| String::from("Not connected to the host"))?;
+        connection.send(&CoopMessage::Action { action })
+    }
+
+    /// Host only: gives the update the next sequence number, applies it
+    /// and sends it to every guest. Cursors and pings get no sequence number
+    fn record(&mut self, update: CoopUpdate) {
+        if matches!(update, CoopUpdate::Cursor { .. } | CoopUpdate::Ping { .. }) {
+            self.broadcast(&CoopMessage::Presence { update: update.clone() });
+            self.show_presence(update);
+            return;
+        }
+        let update = SequencedUpdate { seq: self.next_seq, update };
+        self.next_seq += 1;
+        self.broadcast(&CoopMessage::Update { update: update.clone() });
+        self.apply(update);
+    }
+
+    /// Keeps track of the flag owners, cursors and game of an update, and
+    /// adds it to the list of updates
+    fn apply(&mut self, update: SequencedUpdate) {
+        match &update.update {
+            CoopUpdate::NewGame { game } => {
+                self.game = Some(*game);
+                self.game_id = None;
+                self.flag_owners.clear();
+                self.updates.clear();
+                if self.role == CoopRole::Guest {
+                    self.snapshot = Some(CoopSnapshot {
+                        seq: update.seq,
+                        game: Some(*game),
+                        game_id: None,
+                        cells: Vec::new(),
+                        flag_owners: Vec::new(),
+                        cursors: self.cursors_list()
+                    });
+                }
+            },
+            CoopUpdate::Changes { change, .. } => {
+                self.game_id = Some(change.get_game_id());
+                for cell in change.get_changes() {
+                    let position = cell.get_position();
+                    match (cell.get_state(), cell.get_player()) {
+                        (Some(CellState::Flagged), Some(player)) => { self.flag_owners.insert(position, player as u32); },
+                        (Some(CellState::Flagged), None) => {},
+                        _ => { self.flag_owners.remove(&position); }
+                    }
+                }
+            },
+            CoopUpdate::Cursor { .. } | CoopUpdate::Ping { .. } => {
+                self.show_presence(update.update);
+                return;
+            },
+            CoopUpdate::Rejected { .. } => {}
+        }
+        self.updates.push(update);
+    }
+
+    /// Keeps track of a cursor and sends a cursor or ping to the frontend
+    /// as `coop-presence`
+    fn show_presence(&mut self, update: CoopUpdate) {
+        if let CoopUpdate::Cursor { player_id, x, y } = update {
+            self.cursors.insert(player_id, (x, y));
+        }
+        events::publish_coop_presence(&update);
+    }
+
+    fn cursors_list(&self) -> Vec<(u32, usize, usize)> {
+        self.cursors.iter().map(|(player_id, (x, y))| (*player_id, *x, *y)).collect()
+    }
+
+    /// Host only: the snapshot of the game on `board`
+    fn snapshot(&self, board: &Board) -> CoopSnapshot {
+        let generated = self.game_id == Some(board.get_game_id());
+        CoopSnapshot {
+            seq: self.next_seq - 1,
+            game: self.game,
+            game_id: self.game_id,
+            cells: if generated { board.clone_visible_cells() } else { Vec::new() },
+            flag_owners: self.flag_owners.iter().map(|((x, y), player_id)| (*x, *y, *player_id)).collect(),
+            cursors: self.cursors_list()
+        }
+    }
+
+    fn end(&self) {
+        self.running.store(false, Ordering::Relaxed);
+        for (_, connection) in &self.connections {
+            connection.shutdown();
+        }
+    }
+}
+
+#[tauri::command]
+/// Starts hosting a co-op game on `port` (or the default port), so other
+/// players on the LAN can join. Returns the port that is used
+pub fn host_coop(name: String, port: Option<u16>) -> Result<u16, String> {
+    let mut coop = COOP.lock().map_err(|_| String::from("Couldn't lock the co-op game"))?;
+    if coop.is_some() {
+        return Err(String::from("Already in a co-op game, leave it first"));
+    }
+
+    let (listener, port) = network::listen(port)?;
+    let session = CoopSession::new(CoopRole::Host, name);
+    let running = session.running.clone();
+    *coop = Some(session);
+
+    let tick_running = running.clone();
+    thread::spawn(move || network::accept_connections(listener, running, handle_guest));
+    thread::spawn(move || run_ticks(tick_running));
+    Ok(port)
+}
+
+#[tauri::command]
+/// Joins the co-op game hosted at `address`, which is a host name or IP
+/// address, optionally followed by `:port`
+pub fn join_coop(address: String, name: String) -> Result<(), String> {
+    let mut coop = COOP.lock().map_err(|_| String::from("Couldn't lock the co-op game"))?;
+    if coop.is_some() {
+        return Err(String::from("Already in a co-op game, leave it first"));
+    }
+
+    let stream = network::connect(&address)?;
+    let reader = stream.try_clone().map_err(|e| e.to_string())?;
+    let connection = Connection::new(stream)?;
+    connection.send(&CoopMessage::Join { name: name.clone() })?;
+
+    let mut session = CoopSession::new(CoopRole::Guest, name);
+    // The host's list replaces ours as soon as it arrives
+    session.players.clear();
+    session.connections.push((0, connection));
+    let running = session.running.clone();
+    *coop = Some(session);
+
+    thread::spawn(move || read_from_host(reader, running));
+    Ok(())
+}
+
+#[tauri::command]
+/// Sets up a new game for everyone. Only the host can do this. The board is
+/// generated when the first player pokes a cell, around that cell
+pub fn new_coop_game(width: u32, height: u32, mines: u32, topology: Option<Topology>) -> Result<(), String> {
+    let topology = topology.unwrap_or_default();
+    boardconfig::check_board_size(width, height, mines, settings::get_generation_mode())?;
+
+    let mut coop = COOP.lock().map_err(|_| String::from("Couldn't lock the co-op game"))?;
+    let session = coop.as_mut().ok_or_else(|| String::from("Not in a co-op game"))?;
+    if session.role != CoopRole::Host {
+        return Err(String::from("Only the host can start a new game"));
+    }
+    // Actions from the previous game no longer apply
+    session.pending.clear();
+    session.record(CoopUpdate::NewGame { game: CoopGame { width, height, mines, topology } });
+    Ok(())
+}
+
+#[tauri::command]
+/// Performs `action` as the local player. The result arrives as an update,
+/// once the host has applied it
+pub fn coop_action(action: CoopAction) -> Result<(), String> {
+    let mut coop = COOP.lock().map_err(|_| String::from("Couldn't lock the co-op game"))?;
+    let session = coop.as_mut().ok_or_else(|| String::from("Not in a co-op game"))?;
+    match session.role {
+        CoopRole::Host => {
+            let player_id = session.player_id;
+            session.pending.push((player_id, action));
+            Ok(())
+        },
+        CoopRole::Guest => session.send_to_host(action)
+    }
+}
+
+#[tauri::command]
+/// Returns what is needed to show the current game. Updates after it can be
+/// fetched with `get_coop_updates`, passing the `seq` of the snapshot
+pub fn get_coop_snapshot() -> Result<CoopSnapshot, String> {
+    let current_board = CURRENT_BOARD.lock().map_err(|_| String::from("Couldn't lock the current board"))?;
+    let coop = COOP.lock().map_err(|_| String::from("Couldn't lock the co-op game"))?;
+    let session = coop.as_ref().ok_or_else(|| String::from("Not in a co-op game"))?;
+    match session.role {
+        CoopRole::Host => Ok(session.snapshot(&current_board)),
+        CoopRole::Guest => session.snapshot.clone().ok_or_else(|| String::from("Still waiting for the host"))
+    }
+}
+
+#[tauri::command]
+/// Returns the updates of the current game with a sequence number above `since`.
+/// Cursors and pings aren't included, they are sent as `coop-presence` events
+pub fn get_coop_updates(since: u64) -> Vec<SequencedUpdate> {
+    if let Ok(coop) = COOP.lock() {
+        coop.as_ref()
+            .map(|session| session.updates.iter().filter(|u| u.seq > since).cloned().collect())
+            .unwrap_or_default()
+    } else {
+        println!("ERROR (c.gcu): Couldn't lock the co-op game");
+        Vec::new()
+    }
+}
+
+#[tauri::command]
+/// Returns the players in the co-op game, or `None` when not in one
+pub fn get_coop_status() -> Option<CoopStatus> {
+    if let Ok(coop) = COOP.lock() {
+        coop.as_ref().map(|session| CoopStatus {
+            role: session.role,
+            player_id: session.player_id,
+            players: session.players.clone()
+        })
+    } else {
+        println!("ERROR (c.gcs): Couldn't lock the co-op game");
+        None
+    }
+}
+
+#[tauri::command]
+/// Leaves the co-op game. If this instance is the host, the game ends for everyone
+pub fn leave_coop() {
+    if let Ok(mut coop) = COOP.lock() {
+        if let Some(session) = coop.take() {
+            session.end();
+        }
+    }
+}
+
+/// Runs `f` on the session, if the session that `running` belongs to is still the current one
+fn with_session<T>(running: &Arc<AtomicBool>, f: impl FnOnce(&mut CoopSession) -> T) -> Option<T> {
+    let mut coop = COOP.lock().ok()?;
+    let session = coop.as_mut()?;
+    if Arc::ptr_eq(&session.running, running) {
+        Some(f(session))
+    } else {
+        None
+    }
+}
+
+/// Host thread that applies the gathered actions to the board every tick
+fn run_ticks(running: Arc<AtomicBool>) {
+    while running.load(Ordering::Relaxed) {
+        thread::sleep(TICK);
+        let Some(Some((actions, game, mut game_id))) = with_session(&running, |session| {
+            if session.pending.is_empty() {
+                None
+            } else {
+                Some((std::mem::take(&mut session.pending), session.game, session.game_id))
+            }
+        }) else { continue; };
+
+        let Ok(mut current_board) = CURRENT_BOARD.lock() else {
+            println!("ERROR (c.rt): Couldn't lock current board variable, actions were dropped");
+            continue;
+        };
+        let mut updates = Vec::new();
+        let (applied, rejected) = resolve_conflicts(actions);
+        for (player_id, x, y) in rejected {
+            updates.push(CoopUpdate::Rejected { player_id, x, y });
+        }
+
+        for (player_id, action) in applied {
+            match action {
+                CoopAction::Poke { x, y, game_id: action_game_id } => {
+                    // The first poke of a game generates the board around it
+                    if game_id.is_none() {
+                        match game.map(|game| generate_board(game, x, y)) {
+                            Some(Ok(board)) => {
+                                game_id = Some(board.get_game_id());
+                                *current_board = board;
+                            },
+                            Some(Err(e)) => println!("ERROR (c.rt): Couldn't generate the co-op board: {}", e),
+                            None => {}
+                        }
+                    }
+                    if game_id.is_none() || action_game_id.is_some_and(|id| Some(id) != game_id) {
+                        updates.push(CoopUpdate::Rejected { player_id, x, y });
+                        continue;
+                    }
+                    let change = boardlogic::poke_board(x, y, &mut current_board);
+                    spectate::publish(&current_board, &change);
+                    events::publish(&current_board, &change);
+                    updates.push(CoopUpdate::Changes { player_id, change });
+                },
+                CoopAction::Mark { x, y, game_id: action_game_id } => {
+                    if game_id.is_none() || action_game_id.is_some_and(|id| Some(id) != game_id) {
+                        updates.push(CoopUpdate::Rejected { player_id, x, y });
+                        continue;
+                    }
+                    let mut change = boardlogic::mark_board(x, y, &mut current_board);
+                    change.attribute_flags(player_id as usize);
+                    spectate::publish(&current_board, &change);
+                    events::publish(&current_board, &change);
+                    updates.push(CoopUpdate::Changes { player_id, change });
+                },
+                CoopAction::Cursor { x, y } => updates.push(CoopUpdate::Cursor { player_id, x, y }),
+                CoopAction::Ping { x, y } => updates.push(CoopUpdate::Ping { player_id, x, y })
+            }
+        }
+
+        // Still holding the board, so the updates are recorded in the order they were applied
+        with_session(&running, |session| {
+            session.game_id = game_id;
+            for update in updates {
+                session.record(update);
+            }
+        });
+    }
+}
+
+/// Generates the co-op board with the first poke at `(x, y)`
+fn generate_board(game: CoopGame, x: usize, y: usize) -> Result<Board, String> {
+    let config = BoardConfig::new(game.width, game.height, game.mines, x as u32, y as u32,
+        settings::get_generation_mode())?
+        .with_topology(game.topology)?;
+    let mut board = boardgenerator::create_board(&config, &mut thread_rng());
+    board.set_preset(settings::preset_name_for(&config));
+    // Everyone plays the same board, so it says nothing about the host's own games
+    board.exclude_from_statistics();
+    Ok(board)
+}
+
+/// Picks which of the actions gathered during one tick are applied. Of all
+/// pokes and marks on the same cell, only one goes through: a poke beats a
+/// mark, and between equal actions the lowest player ID wins. The others are
+/// returned as `(player_id, x, y)` to reject. The outcome doesn't depend on
+/// the order in which the actions arrived. Cursors and pings never conflict
+fn resolve_conflicts(mut actions: Vec<PlayerAction>) -> (Vec<PlayerAction>, Vec<(u32, usize, usize)>) {
+    // The sort is stable, so the actions of one player stay in their order
+    actions.sort_by_key(|(player_id, _)| *player_id);
+
+    let mut winners: BTreeMap<(usize, usize), (u8, u32)> = BTreeMap::new();
+    for (player_id, action) in &actions {
+        if let Some((cell, priority)) = action.target() {
+            let winner = winners.entry(cell).or_insert((priority, *player_id));
+            *winner = (*winner).min((priority, *player_id));
+        }
+    }
+
+    let mut applied = Vec::new();
+    let mut rejected = Vec::new();
+    let mut changed_cells = BTreeSet::new();
+    for (player_id, action) in actions {
+        match action.target() {
+            Some((cell, priority)) => {
+                if winners.get(&cell) == Some(&(priority, player_id)) && changed_cells.insert(cell) {
+                    applied.push((player_id, action));
+                } else {
+                    rejected.push((player_id, cell.0, cell.1));
+                }
+            },
+            None => applied.push((player_id, action))
+        }
+    }
+    (applied, rejected)
+}
+
+/// Host thread that handles the messages of one guest until it disconnects
+fn handle_guest(stream: TcpStream, running: Arc<AtomicBool>) {
+    let Ok(reader) = stream.try_clone() else { return; };
+    let Ok(connection) = Connection::new(stream) else { return; };
+    let mut connection = Some(connection);
+    let mut guest_id = None;
+
+    network::read_messages(reader, |message: CoopMessage| match message {
+        CoopMessage::Join { name } => {
+            let Some(connection) = connection.take() else { return; };
+            // The snapshot needs the board, which has to be locked first
+            let Ok(current_board) = CURRENT_BOARD.lock() else { return; };
+            with_session(&running, |session| {
+                let player_id = session.next_player_id;
+                session.next_player_id += 1;
+                guest_id = Some(player_id);
+
+                let snapshot = session.snapshot(&current_board);
+                let _ = connection.send(&CoopMessage::Welcome { player_id, snapshot });
+                session.players.push(CoopPlayer { player_id, name });
+                session.connections.push((player_id, connection));
+                session.broadcast_players();
+            });
+        },
+        CoopMessage::Action { action } => {
+            let Some(player_id) = guest_id else { return; };
+            with_session(&running, |session| session.pending.push((player_id, action)));
+        },
+        _ => {}
+    });
+
+    if let Some(player_id) = guest_id {
+        with_session(&running, |session| {
+            session.players.retain(|p| p.player_id != player_id);
+            session.connections.retain(|(id, _)| *id != player_id);
+            session.cursors.remove(&player_id);
+            session.broadcast_players();
+        });
+    }
+}
+
+/// Guest thread that handles the messages of the host until it disconnects
+fn read_from_host(reader: TcpStream, running: Arc<AtomicBool>) {
+    network::read_messages(reader, |message: CoopMessage| {
+        with_session(&running, |session| match message {
+            CoopMessage::Welcome { player_id, snapshot } => {
+                session.player_id = player_id;
+                session.game = snapshot.game;
+                session.game_id = snapshot.game_id;
+                session.flag_owners = snapshot.flag_owners.iter().map(|(x, y, player_id)| ((*x, *y), *player_id)).collect();
+                session.cursors = snapshot.cursors.iter().map(|(player_id, x, y)| (*player_id, (*x, *y))).collect();
+                session.next_seq = snapshot.seq + 1;
+                session.snapshot = Some(snapshot);
+            },
+            CoopMessage::Players { players } => session.players = players,
+            CoopMessage::Update { update } => {
+                session.next_seq = update.seq + 1;
+                session.apply(update);
+            },
+            CoopMessage::Presence { update } => session.show_presence(update),
+            _ => {}
+        });
+    });
+
+    // The host left, so the game is over
+    if let Ok(mut coop) = COOP.lock() {
+        if coop.as_ref().is_some_and(|session| Arc::ptr_eq(&session.running, &running)) {
+            coop.take();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poke(x: usize, y: usize) -> CoopAction {
+        CoopAction::Poke { x, y, game_id: None }
+    }
+
+    fn mark(x: usize, y: usize) -> CoopAction {
+        CoopAction::Mark { x, y, game_id: None }
+    }
+
+    /// An action as `player_id` and JSON, as actions can't be compared
+    fn describe((player_id, action): &PlayerAction) -> String {
+        format!("{} {}", player_id, serde_json::to_string(action).unwrap())
+    }
+
+    /// The applied actions described, and the rejected ones sorted
+    fn resolve(actions: Vec<PlayerAction>) -> (Vec<String>, Vec<(u32, usize, usize)>) {
+        let (applied, mut rejected) = resolve_conflicts(actions);
+        rejected.sort_unstable();
+        (applied.iter().map(describe).collect(), rejected)
+    }
+
+    #[test]
+    fn pokes_beat_marks_and_lower_player_ids_win() {
+        let actions = vec![
+            (2, mark(1, 1)),
+            (3, poke(1, 1)),
+            (1, mark(1, 1)),
+            (4, poke(1, 1)),
+            (2, mark(0, 0)),
+            (1, mark(0, 0)),
+            (2, CoopAction::Cursor { x: 1, y: 1 }),
+            (3, CoopAction::Ping { x: 0, y: 0 })
+        ];
+        let (applied, rejected) = resolve(actions.clone());
+        let expected: Vec<String> = [
+            (1, mark(0, 0)),
+            (2, CoopAction::Cursor { x: 1, y: 1 }),
+            (3, poke(1, 1)),
+            (3, CoopAction::Ping { x: 0, y: 0 })
+        ].iter().map(describe).collect();
+        assert_eq!(applied, expected);
+        assert_eq!(rejected, vec![(1, 1, 1), (2, 0, 0), (2, 1, 1), (4, 1, 1)]);
+
+        // The order in which the actions arrived doesn't change the outcome
+        let mut reversed = actions;
+        reversed.reverse();
+        let (reversed_applied, reversed_rejected) = resolve(reversed);
+        assert_eq!(reversed_rejected, rejected);
+        assert_eq!(reversed_applied.len(), applied.len());
+        assert!(applied.iter().all(|action| reversed_applied.contains(action)));
+    }
+
+    #[test]
+    fn a_cell_changes_only_once_per_tick() {
+        let (applied, rejected) = resolve(vec![(1, mark(2, 2)), (1, mark(2, 2))]);
+        assert_eq!(applied.len(), 1);
+        assert_eq!(rejected, vec![(1, 2, 2)]);
+    }
+}
@@ -0,0 +1,294 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Serialize, Deserialize};
+
+use crate::boardconfig::BoardConfig;
+use crate::boardgenerator;
+use crate::settings;
+use crate::storage;
+
+/// Preset used for the daily challenge if none is given
+const DEFAULT_DAILY_PRESET: &str = "Intermediate";
+
+/// Name of the daily history file inside the app data directory
+const HISTORY_FILE_NAME: &str = "daily.json";
+
+/// Version of the daily history file written by this build
+const HISTORY_VERSION: u32 = 1;
+
+static HISTORY: Mutex<Vec<DailyEntry>> = Mutex::new(Vec::new());
+static HISTORY_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// One attempt at a daily challenge. Only the first attempt of a date and
+/// preset is recorded, so everyone's results are comparable
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DailyEntry {
+    /// The date of the challenge, formatted as `YYYY-MM-DD` (UTC)
+    date: String,
+    preset: String,
+    /// Whether the game has ended. Unfinished entries were abandoned,
+    /// or are still being played
+    finished: bool,
+    won: bool,
+    /// Duration of the game in milliseconds, once it's finished
+    time: Option<u64>
+}
+
+#[derive(Serialize)]
+pub struct DailyChallenge {
+    date: String,
+    preset: String,
+    /// True if today's challenge for this preset was already attempted
+    /// before, in which case this game doesn't count
+    already_attempted: bool,
+    /// The cell the player has to poke first. It's the same on every install
+    first_x: u32,
+    first_y: u32,
+    width: u32,
+    height: u32,
+    game_id: u32
+}
+
+/// The layout of the daily history file on disk
+#[derive(Serialize, Deserialize)]
+struct HistoryFile {
+    version: u32,
+    entries: Vec<DailyEntry>
+}
+
+#[tauri::command]
+/// Generates today's daily challenge board for a built-in preset and makes
+/// it the current board. Every install generates the same board on the same
+/// (UTC) date, so the first click is fixed to the centre of the board
+pub fn start_daily_challenge(preset: Option<String>) -> Result<DailyChallenge, String> {
+    let preset = preset.unwrap_or_else(|| String::from(DEFAULT_DAILY_PRESET));
+    // User profiles differ between installs, so only the built-in presets can be used
+    let profile = settings::built_in_profiles().into_iter()
+        .find(|p| p.name == preset)
+        .ok_or_else(|| format!("'{}' is not a built-in preset", preset))?;
+
+    let date = today();
+    let config = BoardConfig::new(profile.width, profile.height, profile.mines,
+        profile.width / 2, profile.height / 2, profile.generation_mode)?
+        .with_topology(profile.topology)?;
+    // ChaCha8Rng gives the same numbers for a seed in every version, so
+    // every install agrees on the board
+    let mut rng = ChaCha8Rng::seed_from_u64(daily_seed(&date, &preset));
+    let mut board = boardgenerator::create_board(&config, &mut rng);
+    board.set_preset(profile.name.clone());
+
+    let already_attempted = !start_attempt(&date, &preset);
+    // Only the first attempt counts, a replay would inflate the streak and best time
+    if already_attempted {
+        board.exclude_from_statistics();
+    } else {
+        board.set_daily_date(Some(date.clone()));
+    }
+    let game_id = boardgenerator::set_current_board(board)?;
+
+    Ok(DailyChallenge {
+        date,
+        preset,
+        already_attempted,
+        first_x: config.first_x(),
+        first_y: config.first_y(),
+        width: config.width(),
+        height: config.height(),
+        game_id
+    })
+}
+
+#[tauri::command]
+/// Returns whether today's daily challenge was attempted for `preset`
+pub fn is_daily_attempted(preset: Option<String>) -> bool {
+    let preset = preset.unwrap_or_else(|| String::from(DEFAULT_DAILY_PRESET));
+    let date = today();
+    if let Ok(history) = HISTORY.lock() {
+        history.iter().any(|e| e.date == date && e.preset == preset)
+    } else {
+        println!("ERROR (d.ida): Couldn't lock the daily history");
+        false
+    }
+}
+
+#[tauri::command]
+/// Returns all recorded daily challenges, oldest first
+pub fn get_daily_history() -> Vec<DailyEntry> {
+    if let Ok(history) = HISTORY.lock() {
+        history.clone()
+    } else {
+        println!("ERROR (d.gdh): Couldn't lock the daily history");
+        Vec::new()
+    }
+}
+
+/// Records the result of a finished daily challenge
+pub fn record_result(date: &str, preset: &str, won: bool, duration: Duration) {
+    if let Ok(mut history) = HISTORY.lock() {
+        let entry = history.iter_mut().find(|e| e.date == date && e.preset == preset && !e.finished);
+        if let Some(entry) = entry {
+            entry.finished = true;
+            entry.won = won;
+            entry.time = Some(duration.as_millis() as u64);
+            save_history(&history);
+        }
+    } else {
+        println!("ERROR (d.rr): Couldn't lock the daily history, result wasn't recorded");
+    }
+}
+
+/// Adds an unfinished entry for the challenge. Returns false if the challenge
+/// was already attempted before
+fn start_attempt(date: &str, preset: &str) -> bool {
+    if let Ok(mut history) = HISTORY.lock() {
+        if history.iter().any(|e| e.date == date && e.preset == preset) {
+            return false;
+        }
+        history.push(DailyEntry {
+            date: String::from(date),
+            preset: String::from(preset),
+            finished: false,
+            won: false,
+            time: None
+        });
+        save_history(&history);
+        true
+    } else {
+        println!("ERROR (d.sa): Couldn't lock the daily history");
+        false
+    }
+}
+
+/// The seed of the daily board. This uses FNV-1a rather than the standard
+/// library hasher, because that one may change between Rust versions
+/// and every install has to come up with the same seed
+fn daily_seed(date: &str, preset: &str) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    for byte in date.bytes().chain([b'/']).chain(preset.bytes()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// Today's date in UTC, formatted as `YYYY-MM-DD`
+fn today() -> String {
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (year, month, day) = civil_from_days((seconds / 86400) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Converts a number of days since 1970-01-01 to a (year, month, day) date
+/// in the proleptic Gregorian calendar
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Loads the daily history file from `data_dir`
+pub fn load_history(data_dir: Option<PathBuf>) {
+    let Some(data_dir) = data_dir else {
+        println!("ERROR (d.lh): No app data directory available, daily results won't be saved");
+        return;
+    };
+    let path = data_dir.join(HISTORY_FILE_NAME);
+
+    let loaded = storage::read_json_file(&path).and_then(|value| match value {
+        Some(value) => match serde_json::from_value::<HistoryFile>(value) {
+            Ok(file) if file.version <= HISTORY_VERSION => Ok(file.entries),
+            Ok(file) => Err(format!("daily history version {} is newer than supported version {}", file.version, HISTORY_VERSION)),
+            Err(e) => Err(e.to_string())
+        },
+        None => Ok(Vec::new())
+    });
+    let entries = match loaded {
+        Ok(entries) => {
+            set_history_path(Some(path));
+            entries
+        }
+        Err(e) => {
+            println!("ERROR (d.lh): Couldn't load daily history {}: {}", path.display(), e);
+            match storage::back_up_file(&path) {
+                Ok(backup) => {
+                    println!("ERROR (d.lh): Daily history backed up to {}, starting with an empty history", backup.display());
+                    set_history_path(Some(path));
+                }
+                // Rather lose the results of this session than the file
+                Err(e) => {
+                    println!("ERROR (d.lh): {}, daily results won't be saved", e);
+                    set_history_path(None);
+                }
+            }
+            Vec::new()
+        }
+    };
+
+    if let Ok(mut history) = HISTORY.lock() {
+        *history = entries;
+    }
+}
+
+fn set_history_path(path: Option<PathBuf>) {
+    if let Ok(mut history_path) = HISTORY_PATH.lock() {
+        *history_path = path;
+    } else {
+        println!("ERROR (d.shp): Couldn't lock the daily history path");
+    }
+}
+
+/// Writes `history` to the daily history file, if its location is known
+fn save_history(history: &[DailyEntry]) {
+    let path = match HISTORY_PATH.lock() {
+        Ok(path) => path.clone(),
+        Err(_) => {
+            println!("ERROR (d.sh): Couldn't lock the daily history path");
+            return;
+        }
+    };
+    let Some(path) = path else { return; };
+
+    let file = HistoryFile {
+        version: HISTORY_VERSION,
+        entries: history.to_vec()
+    };
+    if let Err(e) = storage::write_json_file(&path, &file) {
+        println!("ERROR (d.sh): {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn days_are_converted_to_gregorian_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(11016), (2000, 2, 29));
+        assert_eq!(civil_from_days(11017), (2000, 3, 1));
+        assert_eq!(civil_from_days(19782), (2024, 2, 29));
+        // 2100 isn't a leap year
+        assert_eq!(civil_from_days(47540), (2100, 2, 28));
+        assert_eq!(civil_from_days(47541), (2100, 3, 1));
+    }
+
+    #[test]
+    fn the_daily_seed_is_the_same_everywhere() {
+        // Every install has to agree on this value, so it must never change
+        assert_eq!(daily_seed("2024-02-29", "Intermediate"), 0xeb61c4ece5aa6d78);
+        assert_ne!(daily_seed("2024-02-29", "Expert"), daily_seed("2024-02-29", "Intermediate"));
+        assert_ne!(daily_seed("2024-03-01", "Intermediate"), daily_seed("2024-02-29", "Intermediate"));
+    }
+}
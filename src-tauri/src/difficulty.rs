@@ -0,0 +1,141 @@
+use serde::{Serialize, Deserialize};
+
+use crate::board::{Board, CellContent};
+use crate::solver::{Knowledge, Solver, Technique};
+
+/// How much harder a step of each kind makes a board, on top of its 3BV
+const PAIR_WEIGHT: u32 = 2;
+const ENUMERATION_WEIGHT: u32 = 5;
+const GUESS_WEIGHT: u32 = 25;
+
+/// How hard a generated board is to solve
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct Difficulty {
+    /// The least number of clicks that clears the board without flagging
+    bbbv: u32,
+    /// Number of areas without any mines around them, which open at once
+    openings: u32,
+    /// Number of times the solver had to guess to get further
+    forced_guesses: u32,
+    /// The hardest technique the solver needed, if it needed any besides
+    /// the first click
+    hardest_technique: Option<Technique>,
+    /// The overall rating: the 3BV, plus extra for every step that needs a
+    /// harder technique or a guess. Higher is harder
+    rating: u32
+}
+
+impl Difficulty {
+    pub fn rating(&self) -> u32 {
+        self.rating
+    }
+
+    pub fn forced_guesses(&self) -> u32 {
+        self.forced_guesses
+    }
+}
+
+/// Rates `board` by solving it from a first click at `(first_x, first_y)`.
+/// Whenever the solver is stuck, it guesses the cell with the lowest chance
+/// of a mine that is actually safe, so every board can be rated to the end
+pub fn rate_board(board: &Board, first_x: usize, first_y: usize) -> Difficulty {
+    let (bbbv, openings) = count_clicks(board);
+
+    let mut solver = Solver::new(board);
+    let mut hardest_technique = None;
+    let mut pair_steps = 0;
+    let mut enumeration_steps = 0;
+    let mut forced_guesses = 0;
+
+    reveal(board, &mut solver, first_x, first_y);
+    // Until only mines are left
+    while solver.unknown_count() > solver.remaining_mines() {
+        if let Some(deduction) = solver.deduce() {
+            match deduction.technique {
+                Technique::Single => {},
+                Technique::Pair => pair_steps += 1,
+                Technique::Enumeration => enumeration_steps += 1
+            }
+            hardest_technique = hardest_technique.max(Some(deduction.technique));
+            for (x, y) in deduction.mines {
+                solver.mark_mine(x, y);
+            }
+            for (x, y) in deduction.safe {
+                reveal(board, &mut solver, x, y);
+            }
+            continue;
+        }
+
+        let guess = solver.mine_probabilities()
+            .into_iter()
+            .filter(|(x, y, _)| !board.is_mine(*x, *y))
+            .min_by(|a, b| a.2.total_cmp(&b.2));
+        let Some((x, y, _)) = guess else { break; };
+        forced_guesses += 1;
+        reveal(board, &mut solver, x, y);
+    }
+
+    Difficulty {
+        bbbv,
+        openings,
+        forced_guesses,
+        hardest_technique,
+        rating: bbbv + PAIR_WEIGHT * pair_steps + ENUMERATION_WEIGHT * enumeration_steps + GUESS_WEIGHT * forced_guesses
+    }
+}
+
+/// Counts the 3BV and the openings of `board`. Every opening takes one
+/// click, plus one for every number that isn't on the edge of an opening
+pub fn count_clicks(board: &Board) -> (u32, u32) {
+    let width = board.get_width();
+    let mut counted = vec![false; width * board.get_height()];
+    let mut openings = 0;
+
+    for y in 0..board.get_height() {
+        for x in 0..width {
+            if counted[y * width + x] || board.get_content(x, y) != Ok(CellContent::Number(0)) { continue; }
+
+            // One click opens this empty cell, the empty cells connected to
+            // it and the numbers around them
+            openings += 1;
+            counted[y * width + x] = true;
+            let mut stack = vec![(x, y)];
+            while let Some((x, y)) = stack.pop() {
+                for (nx, ny) in board.neighbours(x, y) {
+                    if counted[ny * width + nx] { continue; }
+                    counted[ny * width + nx] = true;
+                    if board.get_content(nx, ny) == Ok(CellContent::Number(0)) {
+                        stack.push((nx, ny));
+                    }
+                }
+            }
+        }
+    }
+
+    let mut numbers = 0;
+    for y in 0..board.get_height() {
+        for x in 0..width {
+            if !counted[y * width + x] && !board.is_mine(x, y) {
+                numbers += 1;
+            }
+        }
+    }
+    (openings + numbers, openings)
+}
+
+/// Uncovers `(x, y)` for the solver, and the cells around it if it's empty.
+/// A fuzzy number is only known by its clue
+fn reveal(board: &Board, solver: &mut Solver, x: usize, y: usize) {
+    let mut stack = vec![(x, y)];
+    while let Some((x, y)) = stack.pop() {
+        if solver.get(x, y) != Knowledge::Unknown { continue; }
+        let Ok(CellContent::Number(value)) = board.get_content(x, y) else { continue; };
+        match board.get_clue(x, y) {
+            Some(clue) => solver.reveal_clue(x, y, clue),
+            None => solver.reveal(x, y, value as u8)
+        }
+        if value == 0 {
+            stack.extend(board.neighbours(x, y));
+        }
+    }
+}
@@ -0,0 +1,443 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use rand::{Rng, SeedableRng, thread_rng};
+use rand_chacha::ChaCha8Rng;
+use serde::Serialize;
+
+use crate::board::{self, CellContent, CellState, GameState};
+use crate::events;
+use crate::settings;
+
+/// Width and height of a chunk, in cells
+const CHUNK_SIZE: i64 = 16;
+
+/// Chance of a mine in a cell if none is given
+const DEFAULT_DENSITY: f64 = 0.18;
+
+/// Lowest allowed chance of a mine. Below it, the cells without mines
+/// around them can form an opening that never ends
+const MIN_DENSITY: f64 = 0.12;
+
+/// Highest allowed chance of a mine
+const MAX_DENSITY: f64 = 0.4;
+
+/// Most cells a single poke opens, in case an opening is very large anyway.
+/// The cells at its edge stay hidden and can be poked to continue
+const MAX_FLOOD_CELLS: usize = 20_000;
+
+/// Most cells that can be asked for at once
+const MAX_REGION_CELLS: i64 = 10_000;
+
+/// Furthest a cell can be from (0, 0) in either direction. Far beyond where
+/// anyone can scroll, and small enough that the cells around it and the
+/// regions next to it can't overflow
+const MAX_COORDINATE: i64 = 1 << 40;
+
+/// The endless game that is being played, if any
+static ENDLESS: Mutex<Option<EndlessBoard>> = Mutex::new(None);
+
+/// A square part of the world. Its mines follow from the seed and its
+/// position, so it can be generated the first time it's needed
+struct Chunk {
+    /// By `y * CHUNK_SIZE + x` within the chunk
+    mines: Vec<bool>,
+    states: Vec<CellState>
+}
+
+/// A board without edges, stored as the chunks that were needed so far.
+/// Cells are addressed by world coordinates, with the first poke at (0, 0)
+struct EndlessBoard {
+    game_id: u32,
+    seed: u64,
+    density: f64,
+    chunks: HashMap<(i64, i64), Chunk>,
+    state: GameState,
+    /// Number of cells without a mine that were uncovered, which is the score
+    cleared: u64,
+    flag_count: u64,
+    start_time: Instant
+}
+
+/// A cell of the endless board as the player sees it
+#[derive(Serialize, Clone)]
+pub struct EndlessCell {
+    x: i64,
+    y: i64,
+    state: CellState,
+    /// What is in the cell, if it's uncovered
+    content: Option<CellContent>
+}
+
+#[derive(Serialize, Clone)]
+pub struct EndlessStatus {
+    game_id: u32,
+    /// Only sent once the game is over, as it gives away where every mine is
+    seed: Option<u64>,
+    game_state: GameState,
+    /// Number of cells cleared
+    score: u64,
+    flag_count: u64,
+    /// Number of chunks generated so far
+    chunk_count: usize,
+    elapsed_ms: u64
+}
+
+#[derive(Serialize, Clone)]
+pub struct EndlessChange {
+    status: EndlessStatus,
+    changes: Vec<EndlessCell>
+}
+
+impl EndlessChange {
+    pub fn get_game_id(&self) -> u32 {
+        self.status.game_id
+    }
+
+    pub fn get_game_state(&self) -> GameState {
+        self.status.game_state
+    }
+}
+
+#[tauri::command]
+/// Starts an endless game from `seed`, or a random seed if none is given.
+/// `density` is the chance of a mine in a cell. The cells around (0, 0)
+/// never hold a mine, so that's where the first poke goes
+pub fn start_endless(seed: Option<u64>, density: Option<f64>) -> Result<EndlessStatus, String> {
+    let density = density.unwrap_or(DEFAULT_DENSITY);
+    if !(MIN_DENSITY..=MAX_DENSITY).contains(&density) {
+        return Err(format!("The mine density must be from {} to {}, not {}", MIN_DENSITY, MAX_DENSITY, density));
+    }
+    let board = EndlessBoard {
+        game_id: board::next_game_id(),
+        seed: seed.unwrap_or_else(|| thread_rng().gen()),
+        density,
+        chunks: HashMap::new(),
+        state: GameState::Playing,
+        cleared: 0,
+        flag_count: 0,
+        start_time: Instant::now()
+    };
+    let status = board.status();
+    let mut endless = ENDLESS.lock().map_err(|_| String::from("Couldn't lock the endless board"))?;
+    *endless = Some(board);
+    events::game_state_changed(status.game_id, status.game_state);
+    Ok(status)
+}
+
+#[tauri::command]
+/// Pokes cell `(x, y)` of the endless board: uncovers a hidden cell, or the
+/// cells around an uncovered one. Openings continue across chunks. If
+/// `game_id` is given and belongs to another game, the board is left alone
+pub fn endless_poke(x: i64, y: i64, game_id: Option<u32>) -> Result<EndlessChange, String> {
+    let mut endless = ENDLESS.lock().map_err(|_| String::from("Couldn't lock the endless board"))?;
+    let board = current_endless(&mut endless, game_id)?;
+    check_position(x, y)?;
+
+    let mut changes = Vec::new();
+    if board.state == GameState::Playing {
+        match board.get_state(x, y) {
+            CellState::Hidden => board.poke_hidden(x, y, &mut changes),
+            CellState::ShowValue => board.poke_shown(x, y, &mut changes),
+            _ => {}
+        }
+    }
+    let change = EndlessChange { status: board.status(), changes };
+    events::publish_endless(&change);
+    Ok(change)
+}
+
+#[tauri::command]
+/// Cycles the mark of cell `(x, y)` of the endless board between flagged,
+/// question-marked (if enabled) and hidden. If `game_id` is given and
+/// belongs to another game, the board is left alone
+pub fn endless_mark(x: i64, y: i64, game_id: Option<u32>) -> Result<EndlessChange, String> {
+    let mut endless = ENDLESS.lock().map_err(|_| String::from("Couldn't lock the endless board"))?;
+    let board = current_endless(&mut endless, game_id)?;
+    check_position(x, y)?;
+
+    let mut changes = Vec::new();
+    if board.state == GameState::Playing {
+        let next_state = match board.get_state(x, y) {
+            CellState::Hidden => Some(CellState::Flagged),
+            CellState::Flagged if settings::is_questions_enabled() => Some(CellState::Questioned),
+            CellState::Flagged | CellState::Questioned => Some(CellState::Hidden),
+            _ => None
+        };
+        if let Some(state) = next_state {
+            board.set_state(x, y, state, &mut changes);
+        }
+    }
+    let change = EndlessChange { status: board.status(), changes };
+    events::publish_endless(&change);
+    Ok(change)
+}
+
+#[tauri::command]
+/// Returns the cells of the endless board from `(x, y)` to
+/// `(x + width - 1, y + height - 1)`, as the player sees them
+pub fn get_endless_region(x: i64, y: i64, width: i64, height: i64) -> Result<Vec<EndlessCell>, String> {
+    if width <= 0 || height <= 0 || width.saturating_mul(height) > MAX_REGION_CELLS {
+        return Err(format!("A region must have 1 to {} cells, not {}x{}", MAX_REGION_CELLS, width, height));
+    }
+    check_position(x, y)?;
+    let endless = ENDLESS.lock().map_err(|_| String::from("Couldn't lock the endless board"))?;
+    let board = endless.as_ref().ok_or_else(|| String::from("No endless game is being played"))?;
+
+    let mut cells = Vec::with_capacity((width * height) as usize);
+    for cell_y in y..y + height {
+        for cell_x in x..x + width {
+            // Chunks that weren't generated yet are all hidden
+            let state = board.get_state(cell_x, cell_y);
+            let content = match state {
+                CellState::ShowValue | CellState::ShowMineExploded => board.get_content(cell_x, cell_y),
+                _ => None
+            };
+            cells.push(EndlessCell { x: cell_x, y: cell_y, state, content });
+        }
+    }
+    Ok(cells)
+}
+
+/// Rejects cells that are further than `MAX_COORDINATE` from (0, 0)
+fn check_position(x: i64, y: i64) -> Result<(), String> {
+    if !is_in_reach(x, y) {
+        return Err(format!("Cell ({}, {}) is more than {} cells away from the start", x, y, MAX_COORDINATE));
+    }
+    Ok(())
+}
+
+/// True if `(x, y)` is at most `MAX_COORDINATE` from (0, 0) in both directions
+fn is_in_reach(x: i64, y: i64) -> bool {
+    (-MAX_COORDINATE..=MAX_COORDINATE).contains(&x) && (-MAX_COORDINATE..=MAX_COORDINATE).contains(&y)
+}
+
+/// The endless board in `endless`, if a game is being played and it is
+/// game `game_id`, or `game_id` isn't given
+fn current_endless(endless: &mut Option<EndlessBoard>, game_id: Option<u32>) -> Result<&mut EndlessBoard, String> {
+    let board = endless.as_mut().ok_or_else(|| String::from("No endless game is being played"))?;
+    if game_id.is_some_and(|id| id != board.game_id) {
+        return Err(format!("Game {} is no longer the endless game", game_id.unwrap_or_default()));
+    }
+    Ok(board)
+}
+
+impl EndlessBoard {
+    fn status(&self) -> EndlessStatus {
+        EndlessStatus {
+            game_id: self.game_id,
+            seed: if self.state == GameState::Playing { None } else { Some(self.seed) },
+            game_state: self.state,
+            score: self.cleared,
+            flag_count: self.flag_count,
+            chunk_count: self.chunks.len(),
+            elapsed_ms: self.start_time.elapsed().as_millis() as u64
+        }
+    }
+
+    /// The chunk `(x, y)` is in, and its index within that chunk
+    fn locate(x: i64, y: i64) -> ((i64, i64), usize) {
+        let chunk = (x.div_euclid(CHUNK_SIZE), y.div_euclid(CHUNK_SIZE));
+        let index = y.rem_euclid(CHUNK_SIZE) * CHUNK_SIZE + x.rem_euclid(CHUNK_SIZE);
+        (chunk, index as usize)
+    }
+
+    /// Returns the chunk at `position`, generating it first if needed
+    fn chunk(&mut self, position: (i64, i64)) -> &mut Chunk {
+        let (seed, density) = (self.seed, self.density);
+        self.chunks.entry(position).or_insert_with(|| generate_chunk(seed, density, position))
+    }
+
+    fn get_state(&self, x: i64, y: i64) -> CellState {
+        let (chunk, index) = EndlessBoard::locate(x, y);
+        self.chunks.get(&chunk).map_or(CellState::Hidden, |chunk| chunk.states[index])
+    }
+
+    fn is_mine(&mut self, x: i64, y: i64) -> bool {
+        let (chunk, index) = EndlessBoard::locate(x, y);
+        self.chunk(chunk).mines[index]
+    }
+
+    /// What is in `(x, y)`, if its chunk was generated
+    fn get_content(&self, x: i64, y: i64) -> Option<CellContent> {
+        let (chunk, index) = EndlessBoard::locate(x, y);
+        let chunk = self.chunks.get(&chunk)?;
+        if chunk.mines[index] {
+            return Some(CellContent::Mines(1));
+        }
+        // The chunks around an uncovered cell were all generated to count its mines
+        let mines = neighbours(x, y).filter(|(nx, ny)| {
+            let (chunk, index) = EndlessBoard::locate(*nx, *ny);
+            self.chunks.get(&chunk).is_some_and(|chunk| chunk.mines[index])
+        }).count();
+        Some(CellContent::Number(mines as i8))
+    }
+
+    /// Counts the mines around `(x, y)`, generating the chunks around it if needed
+    fn count_mines(&mut self, x: i64, y: i64) -> u8 {
+        neighbours(x, y).filter(|(nx, ny)| self.is_mine(*nx, *ny)).count() as u8
+    }
+
+    fn set_state(&mut self, x: i64, y: i64, state: CellState, changes: &mut Vec<EndlessCell>) {
+        let (position, index) = EndlessBoard::locate(x, y);
+        let old_state = self.chunk(position).states[index];
+        self.chunk(position).states[index] = state;
+        if old_state == CellState::Flagged {
+            self.flag_count -= 1;
+        }
+        if state == CellState::Flagged {
+            self.flag_count += 1;
+        }
+        let content = match state {
+            CellState::ShowValue | CellState::ShowMineExploded => self.get_content(x, y),
+            _ => None
+        };
+        changes.push(EndlessCell { x, y, state, content });
+    }
+
+    /// Uncovers `(x, y)`. A cell without mines around it opens the cells
+    /// around it, up to `MAX_FLOOD_CELLS` cells
+    fn poke_hidden(&mut self, x: i64, y: i64, changes: &mut Vec<EndlessCell>) {
+        if self.is_mine(x, y) {
+            self.set_state(x, y, CellState::ShowMineExploded, changes);
+            self.state = GameState::GameOver;
+            self.show_all_mines(changes);
+            return;
+        }
+
+        let mut queue = VecDeque::from([(x, y)]);
+        let mut opened = 0;
+        while let Some((x, y)) = queue.pop_front() {
+            if opened == MAX_FLOOD_CELLS { break; }
+            if self.get_state(x, y) != CellState::Hidden { continue; }
+            // Generate the chunks around the cell before it's shown, so its number is complete
+            let mines = self.count_mines(x, y);
+            self.set_state(x, y, CellState::ShowValue, changes);
+            self.cleared += 1;
+            opened += 1;
+            if mines == 0 {
+                queue.extend(neighbours(x, y).filter(|(nx, ny)| is_in_reach(*nx, *ny)));
+            }
+        }
+    }
+
+    /// Pokes the cells around the uncovered cell `(x, y)` if quick uncover
+    /// is enabled, and the flags around it match its number if protected
+    fn poke_shown(&mut self, x: i64, y: i64, changes: &mut Vec<EndlessCell>) {
+        if !settings::is_quick_uncover_enabled() { return; }
+        if settings::is_protected_uncover_enabled() {
+            let flags = neighbours(x, y).filter(|(nx, ny)| self.get_state(*nx, *ny) == CellState::Flagged).count() as u8;
+            if self.count_mines(x, y) != flags { return; }
+        }
+        for (nx, ny) in neighbours(x, y).filter(|(nx, ny)| is_in_reach(*nx, *ny)) {
+            if self.state == GameState::Playing && self.get_state(nx, ny) == CellState::Hidden {
+                self.poke_hidden(nx, ny, changes);
+            }
+        }
+    }
+
+    /// Shows the mines and wrong flags of every chunk that was generated
+    fn show_all_mines(&mut self, changes: &mut Vec<EndlessCell>) {
+        let mut positions: Vec<(i64, i64)> = self.chunks.keys().copied().collect();
+        positions.sort_unstable();
+        for (chunk_x, chunk_y) in positions {
+            for index in 0..(CHUNK_SIZE * CHUNK_SIZE) {
+                let x = chunk_x * CHUNK_SIZE + index % CHUNK_SIZE;
+                let y = chunk_y * CHUNK_SIZE + index / CHUNK_SIZE;
+                let state = self.get_state(x, y);
+                let mine = self.is_mine(x, y);
+                match state {
+                    CellState::Hidden | CellState::Questioned if mine => self.set_state(x, y, CellState::ShowValue, changes),
+                    CellState::Flagged if !mine => self.set_state(x, y, CellState::ShowInvalidMine, changes),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// The cells around `(x, y)`, which on an endless board always number 8
+fn neighbours(x: i64, y: i64) -> impl Iterator<Item = (i64, i64)> {
+    (-1..=1).flat_map(move |dy| (-1..=1).map(move |dx| (x + dx, y + dy)))
+        .filter(move |cell| *cell != (x, y))
+}
+
+/// Places the mines of the chunk at `position`. The same seed and position
+/// always give the same mines, and the cells around (0, 0) never hold one
+fn generate_chunk(seed: u64, density: f64, (chunk_x, chunk_y): (i64, i64)) -> Chunk {
+    let mut rng = ChaCha8Rng::seed_from_u64(chunk_seed(seed, chunk_x, chunk_y));
+    let mines = (0..CHUNK_SIZE * CHUNK_SIZE)
+        .map(|index| {
+            let mine = rng.gen_bool(density);
+            let x = chunk_x * CHUNK_SIZE + index % CHUNK_SIZE;
+            let y = chunk_y * CHUNK_SIZE + index / CHUNK_SIZE;
+            mine && (x.abs() > 1 || y.abs() > 1)
+        })
+        .collect();
+    Chunk { mines, states: vec![CellState::Hidden; (CHUNK_SIZE * CHUNK_SIZE) as usize] }
+}
+
+/// Mixes the seed of the game with the position of a chunk
+fn chunk_seed(seed: u64, chunk_x: i64, chunk_y: i64) -> u64 {
+    let mut hash = seed;
+    for value in [chunk_x as u64, chunk_y as u64] {
+        hash ^= value.wrapping_add(0x9e3779b97f4a7c15).wrapping_add(hash << 6).wrapping_add(hash >> 2);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn endless_board(seed: u64) -> EndlessBoard {
+        EndlessBoard {
+            game_id: 0,
+            seed,
+            density: MAX_DENSITY,
+            chunks: HashMap::new(),
+            state: GameState::Playing,
+            cleared: 0,
+            flag_count: 0,
+            start_time: Instant::now()
+        }
+    }
+
+    #[test]
+    fn nearby_chunks_get_different_seeds() {
+        let mut seeds = HashSet::new();
+        for seed in 0..4 {
+            for chunk_y in -5..=5 {
+                for chunk_x in -5..=5 {
+                    seeds.insert(chunk_seed(seed, chunk_x, chunk_y));
+                }
+            }
+        }
+        assert_eq!(seeds.len(), 4 * 11 * 11);
+    }
+
+    #[test]
+    fn the_mines_dont_depend_on_the_order_chunks_are_generated_in() {
+        let cells: Vec<(i64, i64)> = (-40..40).flat_map(|y| (-40..40).map(move |x| (x, y))).collect();
+        let mut forwards = endless_board(7);
+        let mut backwards = endless_board(7);
+        let forwards_mines: Vec<bool> = cells.iter().map(|(x, y)| forwards.is_mine(*x, *y)).collect();
+        let mut backwards_mines: Vec<bool> = cells.iter().rev().map(|(x, y)| backwards.is_mine(*x, *y)).collect();
+        backwards_mines.reverse();
+        assert_eq!(forwards_mines, backwards_mines);
+
+        let mut other = endless_board(8);
+        let other_mines: Vec<bool> = cells.iter().map(|(x, y)| other.is_mine(*x, *y)).collect();
+        assert_ne!(forwards_mines, other_mines);
+    }
+
+    #[test]
+    fn the_first_poke_is_never_next_to_a_mine() {
+        for seed in 0..50 {
+            let mut board = endless_board(seed);
+            assert!(neighbours(0, 0).chain([(0, 0)]).all(|(x, y)| !board.is_mine(x, y)));
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::board::{Board, GameState};
+use crate::boardlogic::GameChange;
+use crate::coop::CoopUpdate;
+use crate::endless::EndlessChange;
+use crate::hint::{self, Hint, HintKind};
+use crate::CURRENT_BOARD;
+
+/// The app, once it's set up. Nothing is sent before that.
+/// When both are needed, lock `CURRENT_BOARD` first and this one second
+static APP_HANDLE: Mutex<Option<AppHandle>> = Mutex::new(None);
+
+/// The game ID and state of the last `game-state-changed` event
+static LAST_STATE: Mutex<Option<(u32, GameState)>> = Mutex::new(None);
+
+/// Where boards are sent for the hint thread to look at, once it's started
+static HINT_REQUESTS: Mutex<Option<Sender<Board>>> = Mutex::new(None);
+
+/// How often `timer-tick` is sent while a game is being played
+const TIMER_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Serialize, Clone)]
+struct GameStateChanged {
+    game_id: u32,
+    game_state: GameState
+}
+
+#[derive(Serialize, Clone)]
+struct TimerTick {
+    game_id: u32,
+    elapsed_ms: u64
+}
+
+#[derive(Serialize, Clone)]
+struct HintAvailable {
+    game_id: u32,
+    hint: Hint
+}
+
+/// Keeps `handle` to send events to every window with, and starts sending `timer-tick`
+pub fn init(handle: AppHandle) {
+    match APP_HANDLE.lock() {
+        Ok(mut app) => *app = Some(handle),
+        Err(_) => {
+            println!("ERROR (ev.init): Couldn't lock the app handle, no events will be sent");
+            return;
+        }
+    }
+    thread::spawn(tick_timer);
+
+    let (requests, receiver) = mpsc::channel();
+    match HINT_REQUESTS.lock() {
+        Ok(mut hint_requests) => *hint_requests = Some(requests),
+        Err(_) => println!("ERROR (ev.init): Couldn't lock the hint requests, no hints will be sent")
+    }
+    thread::spawn(move || look_for_hints(receiver));
+}
+
+/// Sends the events for a change to `board`: `board-changed` with the
+/// change, `game-state-changed` if the game is new or its state changed,
+/// and later `hint-available` if the next move can be proven
+pub fn publish(board: &Board, change: &GameChange) {
+    emit("board-changed", change.clone());
+    game_state_changed(board.get_game_id(), board.get_game_state());
+    if board.get_game_state() == GameState::Playing {
+        find_hint(board);
+    }
+}
+
+/// Sends the events for a change to a board that isn't kept here, such as a
+/// race board that the host holds: `board-changed` and `game-state-changed`
+pub fn publish_change(change: &GameChange) {
+    emit("board-changed", change.clone());
+    game_state_changed(change.get_game_id(), change.get_game_state());
+}
+
+/// Sends the events for a change to the endless board: `board-changed` with
+/// the change, and `game-state-changed` if the game is new or its state changed
+pub fn publish_endless(change: &EndlessChange) {
+    emit("board-changed", change.clone());
+    game_state_changed(change.get_game_id(), change.get_game_state());
+}
+
+/// Sends `coop-presence` with a cursor or ping of another co-op player
+pub fn publish_coop_presence(update: &CoopUpdate) {
+    emit("coop-presence", update.clone());
+}
+
+/// Sends `game-state-changed` if `game_id` isn't the game of the last one,
+/// or its state is different
+pub fn game_state_changed(game_id: u32, game_state: GameState) {
+    let Ok(mut last_state) = LAST_STATE.lock() else {
+        println!("ERROR (ev.gsc): Couldn't lock the last game state");
+        return;
+    };
+    if *last_state != Some((game_id, game_state)) {
+        *last_state = Some((game_id, game_state));
+        emit("game-state-changed", GameStateChanged { game_id, game_state });
+    }
+}
+
+/// Has the hint thread look for a proven move on what the player sees of
+/// `board`, so the board isn't held while the solver runs
+fn find_hint(board: &Board) {
+    let Ok(hint_requests) = HINT_REQUESTS.lock() else {
+        println!("ERROR (ev.fh): Couldn't lock the hint requests");
+        return;
+    };
+    if let Some(requests) = hint_requests.as_ref() {
+        // The thread only stops with the app
+        let _ = requests.send(board.visible_copy());
+    }
+}
+
+/// Thread that looks for a proven move on the boards it is sent, and sends
+/// `hint-available` if there is one. Only the newest board is looked at, and
+/// a hint is dropped if another board came in while it was being found or
+/// its game is no longer the current one. A guess isn't worth an event
+fn look_for_hints(requests: Receiver<Board>) {
+    let mut next = None;
+    loop {
+        let board = match next.take() {
+            Some(board) => board,
+            None => match requests.recv() {
+                Ok(board) => board,
+                Err(_) => return
+            }
+        };
+        let board = requests.try_iter().last().unwrap_or(board);
+        let game_id = board.get_game_id();
+        let hint = hint::hint_for(&board);
+
+        next = requests.try_iter().last();
+        if next.is_some() { continue; }
+        let current = CURRENT_BOARD.lock().is_ok_and(|current| current.get_game_id() == game_id);
+        match hint {
+            Ok(hint) if current && hint.kind != HintKind::Guess => emit("hint-available", HintAvailable { game_id, hint }),
+            _ => {}
+        }
+    }
+}
+
+/// Thread that sends the time of the current game while it is being played
+fn tick_timer() {
+    loop {
+        thread::sleep(TIMER_INTERVAL);
+        let tick = {
+            let Ok(board) = CURRENT_BOARD.lock() else { continue; };
+            if board.get_game_state() != GameState::Playing { continue; }
+            TimerTick { game_id: board.get_game_id(), elapsed_ms: board.get_elapsed_time().as_millis() as u64 }
+        };
+        emit("timer-tick", tick);
+    }
+}
+
+fn emit<S: Serialize + Clone>(event: &str, payload: S) {
+    let Ok(app) = APP_HANDLE.lock() else {
+        println!("ERROR (ev.emit): Couldn't lock the app handle, {} wasn't sent", event);
+        return;
+    };
+    if let Some(app) = app.as_ref() {
+        if let Err(e) = app.emit_all(event, payload) {
+            println!("ERROR (ev.emit): Couldn't send {}: {}", event, e);
+        }
+    }
+}
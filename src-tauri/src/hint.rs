@@ -0,0 +1,74 @@
+use serde::Serialize;
+
+use crate::board::{Board, GameState};
+use crate::boardconfig::GameMode;
+use crate::solver::{Solver, Technique};
+use crate::CURRENT_BOARD;
+
+/// What a hint says about its cell
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum HintKind {
+    /// The cell is proven to be safe
+    Safe,
+    /// The cell is proven to be a mine
+    Mine,
+    /// Nothing can be proven, and this cell is the least likely to be a mine
+    Guess
+}
+
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct Hint {
+    pub x: usize,
+    pub y: usize,
+    pub kind: HintKind,
+    /// The technique that proves the cell, if it is proven
+    pub technique: Option<Technique>,
+    /// The chance that the cell is a mine
+    pub mine_chance: f64
+}
+
+#[tauri::command]
+/// Suggests the next move on the current board, from only what the player
+/// can see. Flags are trusted to be on mines. If `game_id` is given and
+/// belongs to another game, no hint is given
+pub fn get_hint(game_id: Option<u32>) -> Result<Hint, String> {
+    let board = CURRENT_BOARD.lock().map_err(|_| String::from("Couldn't lock the current board"))?;
+    if game_id.is_some_and(|id| id != board.get_game_id()) {
+        return Err(format!("Game {} is no longer the current game", game_id.unwrap_or_default()));
+    }
+    hint_for(&board)
+}
+
+/// Suggests the next move on `board`, if it is a classic game that is
+/// being played on a board with standard mines
+pub fn hint_for(board: &Board) -> Result<Hint, String> {
+    if board.get_game_state() != GameState::Playing {
+        return Err(String::from("The game isn't being played"));
+    }
+    if board.get_mode() != GameMode::Classic {
+        return Err(String::from("Hints are only available in classic games"));
+    }
+    if !board.has_standard_mines() {
+        return Err(String::from("Hints aren't available on boards with multi-mine cells or anti-mines"));
+    }
+    find_hint(board).ok_or_else(|| String::from("There are no hidden cells left"))
+}
+
+/// Finds a proven safe cell on `board`, or a proven mine if there is none,
+/// or else the hidden cell with the lowest chance of a mine
+pub fn find_hint(board: &Board) -> Option<Hint> {
+    let solver = Solver::from_visible(board);
+    if let Some(deduction) = solver.deduce() {
+        if let Some((x, y)) = deduction.safe.first() {
+            return Some(Hint { x: *x, y: *y, kind: HintKind::Safe, technique: Some(deduction.technique), mine_chance: 0.0 });
+        }
+        if let Some((x, y)) = deduction.mines.first() {
+            return Some(Hint { x: *x, y: *y, kind: HintKind::Mine, technique: Some(deduction.technique), mine_chance: 1.0 });
+        }
+    }
+
+    solver.mine_probabilities()
+        .into_iter()
+        .min_by(|a, b| a.2.total_cmp(&b.2))
+        .map(|(x, y, mine_chance)| Hint { x, y, kind: HintKind::Guess, technique: None, mine_chance })
+}
@@ -1,10 +1,29 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod analysis;
+mod benchmark;
 mod board;
+mod boardconfig;
 mod boardgenerator;
 mod boardlogic;
+mod bot;
+mod coop;
+mod daily;
+mod difficulty;
+mod endless;
+mod events;
+mod hint;
+mod network;
+mod puzzle;
+mod race;
 mod settings;
+mod simulation;
+mod solver;
+mod spectate;
+mod statistics;
+mod storage;
+mod trainer;
 
 use std::sync::Mutex;
 use board::Board;
@@ -14,9 +33,43 @@ static CURRENT_BOARD: Mutex<Board> = Mutex::new(Board::empty());
 static GAME_SETTINGS: Mutex<Settings> = Mutex::new(Settings::new());
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    // These only print their results. Release builds on Windows have no
+    // console (see the top of this file), so there they print nothing; use
+    // a debug build, e.g. `cargo run -- --simulate 30 16 99`
+    if args.iter().any(|arg| arg == "--benchmark") {
+        benchmark::run();
+        return;
+    }
+    if let Some(index) = args.iter().position(|arg| arg == "--simulate") {
+        simulation::run(&args[index + 1..]);
+        return;
+    }
+
     tauri::Builder::default()
+        .setup(|app| {
+            settings::load_settings(app.path_resolver().app_config_dir());
+            statistics::load_statistics(app.path_resolver().app_data_dir());
+            daily::load_history(app.path_resolver().app_data_dir());
+            events::init(app.handle());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![boardgenerator::generate_board, 
-            boardlogic::poke, boardlogic::mark, settings::set_game_settings])
+            boardlogic::poke, boardlogic::mark, boardlogic::get_visible_cells, settings::set_game_settings,
+            settings::get_game_settings, settings::get_profiles, settings::create_profile,
+            settings::rename_profile, settings::delete_profile, settings::select_profile,
+            statistics::get_statistics, statistics::reset_statistics,
+            daily::start_daily_challenge, daily::is_daily_attempted, daily::get_daily_history,
+            race::host_race, race::join_race, race::start_race, race::get_race_status,
+            race::race_action, race::get_race_board, race::leave_race,
+            coop::host_coop, coop::join_coop, coop::new_coop_game, coop::coop_action,
+            coop::get_coop_snapshot, coop::get_coop_updates, coop::get_coop_status, coop::leave_coop,
+            spectate::start_broadcast, spectate::stop_broadcast, spectate::watch_game,
+            spectate::stop_watching, spectate::get_spectator_view,
+            trainer::start_drill, trainer::submit_drill, analysis::analyze_game,
+            puzzle::generate_puzzle, puzzle::submit_puzzle, hint::get_hint, bot::bot_step,
+            bot::start_auto_play, bot::stop_auto_play, simulation::simulate_games,
+            endless::start_endless, endless::endless_poke, endless::endless_mark, endless::get_endless_region])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -0,0 +1,115 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+/// TCP port used for LAN games if the host doesn't choose one
+pub const DEFAULT_PORT: u16 = 47615;
+
+/// Sends `message` over `stream` as a single line of JSON
+pub fn send_message<T: Serialize>(mut stream: &TcpStream, message: &T) -> Result<(), String> {
+    let mut line = serde_json::to_string(message).map_err(|e| e.to_string())?;
+    line.push('\n');
+    stream.write_all(line.as_bytes()).map_err(|e| e.to_string())
+}
+
+/// A connection whose messages are written by a thread of its own, so
+/// sending never waits for the network while a lock is held
+pub struct Connection {
+    stream: TcpStream,
+    lines: Sender<String>
+}
+
+impl Connection {
+    /// Starts the thread that writes the messages sent over `stream`
+    pub fn new(stream: TcpStream) -> Result<Connection, String> {
+        let mut writer = stream.try_clone().map_err(|e| e.to_string())?;
+        let (lines, receiver) = mpsc::channel::<String>();
+        thread::spawn(move || {
+            for line in receiver {
+                if let Err(e) = writer.write_all(line.as_bytes()) {
+                    println!("ERROR (n.c): Couldn't send a message: {}", e);
+                    break;
+                }
+            }
+        });
+        Ok(Connection { stream, lines })
+    }
+
+    /// Queues `message` to be sent as a single line of JSON. Fails once
+    /// sending stopped because the connection broke
+    pub fn send<T: Serialize>(&self, message: &T) -> Result<(), String> {
+        let mut line = serde_json::to_string(message).map_err(|e| e.to_string())?;
+        line.push('\n');
+        self.lines.send(line).map_err(|_| String::from("The connection is closed"))
+    }
+
+    /// Closes the connection, which also ends the thread reading from it
+    pub fn shutdown(&self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+/// Reads JSON messages from `stream`, one per line, and passes them to
+/// `handler` until the connection is closed. Lines that aren't a valid
+/// message are skipped
+pub fn read_messages<T: DeserializeOwned>(stream: TcpStream, mut handler: impl FnMut(T)) {
+    let reader = BufReader::new(stream);
+    for line in reader.lines() {
+        let Ok(line) = line else { break; };
+        match serde_json::from_str::<T>(&line) {
+            Ok(message) => handler(message),
+            Err(e) => println!("ERROR (n.rm): Received an invalid message: {}", e)
+        }
+    }
+}
+
+/// Binds a listener for a LAN session on `port`, or the default port.
+/// The listener doesn't block, so `accept_connections` can notice when the
+/// session ends
+pub fn listen(port: Option<u16>) -> Result<(TcpListener, u16), String> {
+    let listener = TcpListener::bind(("0.0.0.0", port.unwrap_or(DEFAULT_PORT)))
+        .map_err(|e| format!("Couldn't start hosting: {}", e))?;
+    let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+    listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+    Ok((listener, port))
+}
+
+/// Connects to a host at `address`, which is a host name or IP address,
+/// optionally followed by `:port`
+pub fn connect(address: &str) -> Result<TcpStream, String> {
+    let address = if address.contains(':') {
+        String::from(address)
+    } else {
+        format!("{}:{}", address, DEFAULT_PORT)
+    };
+    TcpStream::connect(&address).map_err(|e| format!("Couldn't connect to {}: {}", address, e))
+}
+
+/// Accepts connections on `listener` until `running` is cleared, and
+/// handles each of them on its own thread with `handler`
+pub fn accept_connections(listener: TcpListener, running: Arc<AtomicBool>, handler: fn(TcpStream, Arc<AtomicBool>)) {
+    while running.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                if let Err(e) = stream.set_nonblocking(false) {
+                    println!("ERROR (n.ac): Couldn't set up connection: {}", e);
+                    continue;
+                }
+                let running = running.clone();
+                thread::spawn(move || handler(stream, running));
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => thread::sleep(Duration::from_millis(100)),
+            Err(e) => {
+                println!("ERROR (n.ac): Stopped accepting connections: {}", e);
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+use serde::Serialize;
+
+use crate::board::{Board, CellContent, CellDetails, CellState};
+use crate::boardconfig::{BoardConfig, GameMode, Topology};
+use crate::boardgenerator;
+use crate::boardlogic::{self, GameChange};
+use crate::events;
+use crate::settings::GenerationMode;
+use crate::solver::{Solver, Technique};
+use crate::spectate;
+use crate::CURRENT_BOARD;
+
+/// Largest number of cells of a puzzle. Choosing the clues runs the solver
+/// once for every cell, which gets slow on large boards
+const MAX_PUZZLE_CELLS: u32 = 900;
+
+#[derive(Serialize)]
+pub struct Puzzle {
+    /// The ID to pass to `submit_puzzle`
+    game_id: u32,
+    /// What the player can see of the board: the clues, everything else hidden
+    cells: Vec<Vec<CellDetails>>
+}
+
+#[tauri::command(async)]
+/// Generates a puzzle: a board where some of the cells without a mine are
+/// uncovered as clues, and the player has to flag all mines. The clues are
+/// chosen so there is exactly one way to place the mines. Runs off the main
+/// thread, as choosing the clues can take a while on large boards
+pub fn generate_puzzle(width: u32, height: u32, mines: u32, topology: Option<Topology>) -> Result<Puzzle, String> {
+    // There's no first click, but the config needs one. Only that cell is kept free of mines.
+    // The config is checked first, so the size can't overflow below
+    let config = BoardConfig::new(width, height, mines, width / 2, height / 2, GenerationMode::SafeCell)?
+        .with_topology(topology.unwrap_or_default())?
+        .with_mode(GameMode::Puzzle)?;
+    if width * height > MAX_PUZZLE_CELLS {
+        return Err(format!("A puzzle can have at most {} cells, but {}x{} has {}", MAX_PUZZLE_CELLS, width, height, width * height));
+    }
+
+    let mut rng = thread_rng();
+    let mut board = boardgenerator::create_board(&config, &mut rng);
+    for (x, y) in choose_clues(&board, &mut rng) {
+        board.set_state(x, y, CellState::ShowValue)?;
+        board.subtract_hidden_cell();
+    }
+
+    let cells = board.clone_visible_cells();
+    let game_id = boardgenerator::set_current_board(board)?;
+    Ok(Puzzle { game_id, cells })
+}
+
+#[tauri::command]
+/// Submits the flags of the puzzle on the current board. Ends the game,
+/// won if exactly the mines are flagged. If `game_id` is given and belongs
+/// to another game, the board is left alone
+pub fn submit_puzzle(game_id: Option<u32>) -> Result<GameChange, String> {
+    let mut board = CURRENT_BOARD.lock().map_err(|_| String::from("Couldn't lock the current board"))?;
+    if game_id.is_some_and(|id| id != board.get_game_id()) {
+        return Err(format!("Game {} is no longer the current game", game_id.unwrap_or_default()));
+    }
+    if board.get_mode() != GameMode::Puzzle {
+        return Err(String::from("The current game isn't a puzzle"));
+    }
+
+    let change = boardlogic::submit_puzzle_board(&mut board);
+    spectate::publish(&board, &change);
+    events::publish(&board, &change);
+    Ok(change)
+}
+
+/// Starts with every cell without a mine as a clue, and removes clues in a
+/// random order for as long as the solver can still prove where every mine is
+fn choose_clues(board: &Board, rng: &mut impl Rng) -> Vec<(usize, usize)> {
+    let mut clues: Vec<(usize, usize)> = (0..board.get_height())
+        .flat_map(|y| (0..board.get_width()).map(move |x| (x, y)))
+        .filter(|(x, y)| !board.is_mine(*x, *y))
+        .collect();
+    let mut order = clues.clone();
+    order.shuffle(rng);
+
+    for clue in order {
+        let without: Vec<(usize, usize)> = clues.iter().copied().filter(|c| *c != clue).collect();
+        if has_unique_solution(board, &without) {
+            clues = without;
+        }
+    }
+    clues
+}
+
+/// True if the solver can find every mine on `board` from only `clues`
+fn has_unique_solution(board: &Board, clues: &[(usize, usize)]) -> bool {
+    let mut solver = Solver::new(board);
+    for (x, y) in clues {
+        if let Ok(CellContent::Number(value)) = board.get_content(*x, *y) {
+            solver.reveal(*x, *y, value as u8);
+        }
+    }
+    solver.forced_cells(Technique::Enumeration).mines.len() == board.get_mine_total()
+}
@@ -0,0 +1,524 @@
+use std::collections::HashMap;
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use rand::{Rng, SeedableRng, thread_rng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Serialize, Deserialize};
+
+use crate::board::{Board, CellContent, CellDetails, CellState, GameState};
+use crate::boardconfig::{BoardConfig, Topology};
+use crate::boardgenerator;
+use crate::boardlogic::{self, GameChange};
+use crate::events;
+use crate::network::{self, Connection};
+use crate::settings::{self, GenerationMode};
+use crate::CURRENT_BOARD;
+
+/// The race this instance is hosting or has joined, if any.
+/// Never lock `CURRENT_BOARD` while holding this lock: the board logic
+/// locks this one while it holds the board
+static RACE: Mutex<Option<RaceSession>> = Mutex::new(None);
+
+/// The messages sent between the host and the peers, one JSON object per line
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum RaceMessage {
+    /// Sent by a peer right after connecting
+    Join { name: String },
+    /// Sent by the host to a peer that joined, with the ID it got
+    Welcome { player_id: u32 },
+    /// Sent by the host whenever a player joins, leaves or makes progress
+    Players { players: Vec<PlayerProgress> },
+    /// Sent by the host to a peer to start a race. Only the host knows
+    /// where the mines are, so the peer starts with every cell hidden.
+    /// `game_id` is the ID of the board the host keeps for the peer
+    Start { race: RaceInfo, game_id: u32 },
+    /// Sent by a peer for each of its moves on the race board
+    Action { action: RaceAction },
+    /// Sent by the host to a peer with the result of its move
+    Changes { change: GameChange }
+}
+
+/// A move on the race board
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(tag = "type")]
+pub enum RaceAction {
+    Poke { x: usize, y: usize },
+    Mark { x: usize, y: usize }
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+pub enum RaceRole {
+    Host,
+    Peer
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PlayerProgress {
+    player_id: u32,
+    name: String,
+    cells_revealed: usize,
+    /// The mine that exploded, and once the game is over, the flags on
+    /// cells without a mine. Wrong flags aren't counted before that, as
+    /// the count would tell the players which cells hold a mine
+    mistakes: usize,
+    game_state: GameState,
+    /// Milliseconds from the start of the race until the player won or lost
+    finish_time: Option<u64>
+}
+
+impl PlayerProgress {
+    fn new(player_id: u32, name: String) -> PlayerProgress {
+        PlayerProgress {
+            player_id,
+            name,
+            cells_revealed: 0,
+            mistakes: 0,
+            game_state: GameState::BeforeGame,
+            finish_time: None
+        }
+    }
+}
+
+/// The board everyone races on, without what is under its cells
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct RaceInfo {
+    race_id: u32,
+    config: BoardConfig
+}
+
+/// Host only: the boards of the current race. The seed never leaves the
+/// host, as it gives away where every mine is
+struct HostedRace {
+    seed: u64,
+    /// The board of every peer, by player ID
+    boards: HashMap<u32, Board>
+}
+
+struct RaceSession {
+    role: RaceRole,
+    player_id: u32,
+    players: Vec<PlayerProgress>,
+    /// For the host, one connection per peer. For a peer, only the
+    /// connection to the host (with the host's player ID, 0)
+    connections: Vec<(u32, Connection)>,
+    race: Option<RaceInfo>,
+    /// ID of the board this player plays the current race on. For a peer,
+    /// that board is kept by the host
+    game_id: Option<u32>,
+    hosted: Option<HostedRace>,
+    /// Peer only: the race board as the player sees it
+    cells: Vec<Vec<CellDetails>>,
+    next_player_id: u32,
+    next_race_id: u32,
+    /// Cleared when the session ends, which stops its threads
+    running: Arc<AtomicBool>
+}
+
+impl RaceSession {
+    fn new(role: RaceRole, name: String) -> RaceSession {
+        RaceSession {
+            role,
+            player_id: 0,
+            players: vec![PlayerProgress::new(0, name)],
+            connections: Vec::new(),
+            race: None,
+            game_id: None,
+            hosted: None,
+            cells: Vec::new(),
+            next_player_id: 1,
+            next_race_id: 1,
+            running: Arc::new(AtomicBool::new(true))
+        }
+    }
+
+    /// Sends `message` to every connection of this session
+    fn broadcast(&self, message: &RaceMessage) {
+        for (player_id, connection) in &self.connections {
+            if let Err(e) = connection.send(message) {
+                println!("ERROR (r.b): Couldn't send a message to player {}: {}", player_id, e);
+            }
+        }
+    }
+
+    fn broadcast_players(&self) {
+        self.broadcast(&RaceMessage::Players { players: self.players.clone() });
+    }
+
+    /// Sends `message` to the connection of `player_id` only
+    fn send_to(&self, player_id: u32, message: &RaceMessage) {
+        if let Some((_, connection)) = self.connections.iter().find(|(id, _)| *id == player_id) {
+            if let Err(e) = connection.send(message) {
+                println!("ERROR (r.st): Couldn't send a message to player {}: {}", player_id, e);
+            }
+        }
+    }
+
+    /// Host only: generates the race board for `player_id` and sends it the start of the race
+    fn start_peer(&mut self, player_id: u32) {
+        let (Some(race), Some(hosted)) = (self.race, self.hosted.as_mut()) else { return; };
+        let mut board = race_board(&race.config, hosted.seed);
+        // The peer's games are played here, but they aren't the host's
+        board.exclude_from_statistics();
+        let game_id = board.get_game_id();
+        hosted.boards.insert(player_id, board);
+        self.send_to(player_id, &RaceMessage::Start { race, game_id });
+    }
+
+    fn end(&self) {
+        self.running.store(false, Ordering::Relaxed);
+        for (_, connection) in &self.connections {
+            connection.shutdown();
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct RaceStatus {
+    role: RaceRole,
+    player_id: u32,
+    players: Vec<PlayerProgress>,
+    race: Option<RaceInfo>,
+    game_id: Option<u32>
+}
+
+#[tauri::command]
+/// Starts hosting a race on `port` (or the default port), so peers on the
+/// LAN can join. Returns the port that is used
+pub fn host_race(name: String, port: Option<u16>) -> Result<u16, String> {
+    let mut race = RACE.lock().map_err(|_| String::from("Couldn't lock the race"))?;
+    if race.is_some() {
+        return Err(String::from("Already in a race, leave it first"));
+    }
+
+    let (listener, port) = network::listen(port)?;
+    let session = RaceSession::new(RaceRole::Host, name);
+    let running = session.running.clone();
+    *race = Some(session);
+
+    thread::spawn(move || network::accept_connections(listener, running, handle_peer));
+    Ok(port)
+}
+
+#[tauri::command]
+/// Joins the race hosted at `address`, which is a host name or IP address,
+/// optionally followed by `:port`
+pub fn join_race(address: String, name: String) -> Result<(), String> {
+    let mut race = RACE.lock().map_err(|_| String::from("Couldn't lock the race"))?;
+    if race.is_some() {
+        return Err(String::from("Already in a race, leave it first"));
+    }
+
+    let stream = network::connect(&address)?;
+    let reader = stream.try_clone().map_err(|e| e.to_string())?;
+    let connection = Connection::new(stream)?;
+    connection.send(&RaceMessage::Join { name: name.clone() })?;
+
+    let mut session = RaceSession::new(RaceRole::Peer, name);
+    // The host's list replaces ours as soon as it arrives
+    session.players.clear();
+    session.connections.push((0, connection));
+    let running = session.running.clone();
+    *race = Some(session);
+
+    thread::spawn(move || read_from_host(reader, running));
+    Ok(())
+}
+
+#[tauri::command]
+/// Starts a new race for everyone in the session. Only the host can do this.
+/// The first click is fixed to the centre of the board, so that everyone
+/// gets the same board. The host keeps every board and applies the moves
+/// of the peers, so the peers never learn where the mines are
+pub fn start_race(width: u32, height: u32, mines: u32, topology: Option<Topology>) -> Result<RaceInfo, String> {
+    let config = BoardConfig::new(width, height, mines, width / 2, height / 2, GenerationMode::SafeArea)?
+        .with_topology(topology.unwrap_or_default())?;
+    let seed = thread_rng().gen();
+    let race = {
+        let mut race = RACE.lock().map_err(|_| String::from("Couldn't lock the race"))?;
+        let session = race.as_mut().ok_or_else(|| String::from("Not in a race"))?;
+        if session.role != RaceRole::Host {
+            return Err(String::from("Only the host can start a race"));
+        }
+        let info = RaceInfo { race_id: session.next_race_id, config };
+        session.next_race_id += 1;
+        info
+    };
+
+    // The board is set up without holding the race lock
+    let mut board = race_board(&config, seed);
+    board.set_preset(settings::preset_name_for(&config));
+    let game_id = boardgenerator::set_current_board(board)?;
+
+    let mut race_lock = RACE.lock().map_err(|_| String::from("Couldn't lock the race"))?;
+    let session = race_lock.as_mut().ok_or_else(|| String::from("Not in a race"))?;
+    set_race(session, race, game_id);
+    session.hosted = Some(HostedRace { seed, boards: HashMap::new() });
+    let peers: Vec<u32> = session.connections.iter().map(|(player_id, _)| *player_id).collect();
+    for player_id in peers {
+        session.start_peer(player_id);
+    }
+    session.broadcast_players();
+    Ok(race)
+}
+
+#[tauri::command]
+/// Makes a move on the local race board. The host plays on the current
+/// board, a peer's move is applied by the host and its changes arrive as
+/// `board-changed`
+pub fn race_action(action: RaceAction) -> Result<(), String> {
+    let game_id = {
+        let race = RACE.lock().map_err(|_| String::from("Couldn't lock the race"))?;
+        let session = race.as_ref().ok_or_else(|| String::from("Not in a race"))?;
+        let game_id = session.game_id.ok_or_else(|| String::from("No race has started"))?;
+        if session.role == RaceRole::Peer {
+            let (_, connection) = session.connections.first().ok_or_else(|| String::from("Not connected to the host"))?;
+            return connection.send(&RaceMessage::Action { action });
+        }
+        game_id
+    };
+
+    // The board logic locks the race itself, after the board
+    match action {
+        RaceAction::Poke { x, y } => boardlogic::poke(x, y, Some(game_id)),
+        RaceAction::Mark { x, y } => boardlogic::mark(x, y, Some(game_id))
+    };
+    Ok(())
+}
+
+#[tauri::command]
+/// Returns the players and their progress in the current race, or
+/// `None` when not in a race
+pub fn get_race_status() -> Option<RaceStatus> {
+    if let Ok(race) = RACE.lock() {
+        race.as_ref().map(|session| RaceStatus {
+            role: session.role,
+            player_id: session.player_id,
+            players: session.players.clone(),
+            race: session.race,
+            game_id: session.game_id
+        })
+    } else {
+        println!("ERROR (r.grs): Couldn't lock the race");
+        None
+    }
+}
+
+#[tauri::command]
+/// Returns the cells of the local race board as the player sees them, once a race has started
+pub fn get_race_board() -> Result<Vec<Vec<CellDetails>>, String> {
+    let game_id = {
+        let race = RACE.lock().map_err(|_| String::from("Couldn't lock the race"))?;
+        let session = race.as_ref().ok_or_else(|| String::from("Not in a race"))?;
+        let game_id = session.game_id.ok_or_else(|| String::from("No race has started"))?;
+        if session.role == RaceRole::Peer {
+            return Ok(session.cells.clone());
+        }
+        game_id
+    };
+    let current_board = CURRENT_BOARD.lock().map_err(|_| String::from("Couldn't lock the current board"))?;
+    if current_board.get_game_id() != game_id {
+        return Err(String::from("The race board is no longer the current board"));
+    }
+    Ok(current_board.clone_visible_cells())
+}
+
+#[tauri::command]
+/// Leaves the current race. If this instance is the host, the race ends for everyone
+pub fn leave_race() {
+    if let Ok(mut race) = RACE.lock() {
+        if let Some(session) = race.take() {
+            session.end();
+        }
+    }
+}
+
+/// Sends the host's progress on `board` to the peers, if it's the race board.
+/// Called by the board logic after every move, while it holds `CURRENT_BOARD`.
+/// The progress of the peers is kept by the host, which holds their boards
+pub fn report_progress(board: &Board) {
+    let Ok(mut race) = RACE.lock() else {
+        println!("ERROR (r.rp): Couldn't lock the race");
+        return;
+    };
+    let Some(session) = race.as_mut() else { return; };
+    if session.role != RaceRole::Host || session.game_id != Some(board.get_game_id()) {
+        return;
+    }
+
+    let player_id = session.player_id;
+    if let Some(progress) = session.players.iter_mut().find(|p| p.player_id == player_id) {
+        update_progress(progress, board);
+        session.broadcast_players();
+    }
+}
+
+/// Generates the board of a race. Every player gets the same board from the same seed
+fn race_board(config: &BoardConfig, seed: u64) -> Board {
+    boardgenerator::create_board(config, &mut ChaCha8Rng::seed_from_u64(seed))
+}
+
+fn update_progress(progress: &mut PlayerProgress, board: &Board) {
+    progress.cells_revealed = board.get_revealed_cell_count();
+    progress.mistakes = count_mistakes(board);
+    progress.game_state = board.get_game_state();
+    if board.get_game_state() != GameState::Playing && progress.finish_time.is_none() {
+        progress.finish_time = Some(board.get_elapsed_time().as_millis() as u64);
+    }
+}
+
+/// Stores the started race in the session and resets everyone's progress
+fn set_race(session: &mut RaceSession, race: RaceInfo, game_id: u32) {
+    session.race = Some(race);
+    session.game_id = Some(game_id);
+    for player in session.players.iter_mut() {
+        *player = PlayerProgress {
+            game_state: GameState::Playing,
+            ..PlayerProgress::new(player.player_id, player.name.clone())
+        };
+    }
+}
+
+/// Runs `f` on the session, if the session that `running` belongs to is still the current one
+fn with_session(running: &Arc<AtomicBool>, f: impl FnOnce(&mut RaceSession)) {
+    if let Ok(mut race) = RACE.lock() {
+        if let Some(session) = race.as_mut() {
+            if Arc::ptr_eq(&session.running, running) {
+                f(session);
+            }
+        }
+    }
+}
+
+/// Host thread that handles the messages of one peer until it disconnects
+fn handle_peer(stream: TcpStream, running: Arc<AtomicBool>) {
+    let Ok(reader) = stream.try_clone() else { return; };
+    let Ok(connection) = Connection::new(stream) else { return; };
+    let mut connection = Some(connection);
+    let mut peer_id = None;
+
+    network::read_messages(reader, |message: RaceMessage| match message {
+        RaceMessage::Join { name } => {
+            let Some(connection) = connection.take() else { return; };
+            with_session(&running, |session| {
+                let player_id = session.next_player_id;
+                session.next_player_id += 1;
+                peer_id = Some(player_id);
+
+                let _ = connection.send(&RaceMessage::Welcome { player_id });
+                let mut progress = PlayerProgress::new(player_id, name);
+                if session.race.is_some() {
+                    progress.game_state = GameState::Playing;
+                }
+                session.players.push(progress);
+                session.connections.push((player_id, connection));
+                // Players joining during a race can still play it, they just start late
+                session.start_peer(player_id);
+                session.broadcast_players();
+            });
+        },
+        RaceMessage::Action { action } => {
+            let Some(player_id) = peer_id else { return; };
+            with_session(&running, |session| apply_action(session, player_id, action));
+        },
+        _ => {}
+    });
+
+    if let Some(player_id) = peer_id {
+        with_session(&running, |session| {
+            session.players.retain(|p| p.player_id != player_id);
+            session.connections.retain(|(id, _)| *id != player_id);
+            if let Some(hosted) = session.hosted.as_mut() {
+                hosted.boards.remove(&player_id);
+            }
+            session.broadcast_players();
+        });
+    }
+}
+
+/// Host only: applies the move of peer `player_id` to its race board and
+/// sends it the changes
+fn apply_action(session: &mut RaceSession, player_id: u32, action: RaceAction) {
+    let Some(board) = session.hosted.as_mut().and_then(|hosted| hosted.boards.get_mut(&player_id)) else { return; };
+    let change = match action {
+        RaceAction::Poke { x, y } => boardlogic::poke_board(x, y, board),
+        RaceAction::Mark { x, y } => boardlogic::mark_board(x, y, board)
+    };
+    if let Some(progress) = session.players.iter_mut().find(|p| p.player_id == player_id) {
+        update_progress(progress, board);
+    }
+    session.send_to(player_id, &RaceMessage::Changes { change });
+    session.broadcast_players();
+}
+
+/// Peer thread that handles the messages of the host until it disconnects
+fn read_from_host(reader: TcpStream, running: Arc<AtomicBool>) {
+    network::read_messages(reader, |message: RaceMessage| match message {
+        RaceMessage::Welcome { player_id } => with_session(&running, |session| session.player_id = player_id),
+        RaceMessage::Players { players } => with_session(&running, |session| session.players = players),
+        RaceMessage::Start { race, game_id } => with_session(&running, |session| {
+            // Nothing is uncovered yet, so an empty board of the same size is what the player sees
+            session.cells = Board::new(&race.config).clone_visible_cells();
+            set_race(session, race, game_id);
+        }),
+        RaceMessage::Changes { change } => with_session(&running, |session| {
+            if session.game_id == Some(change.get_game_id()) {
+                change.apply_to(&mut session.cells);
+                events::publish_change(&change);
+            }
+        }),
+        _ => {}
+    });
+
+    // The host left, so the session is over
+    if let Ok(mut race) = RACE.lock() {
+        if race.as_ref().is_some_and(|session| Arc::ptr_eq(&session.running, &running)) {
+            race.take();
+        }
+    }
+}
+
+/// Counts the mistakes the player can see: cells shown as a wrong flag or
+/// an exploded mine. Once the game is over, flags on cells without a mine
+/// count too
+fn count_mistakes(board: &Board) -> usize {
+    let finished = board.get_game_state() != GameState::Playing;
+    let mut mistakes = 0;
+    for x in 0..board.get_width() {
+        for y in 0..board.get_height() {
+            match (board.get_state(x, y), board.get_content(x, y)) {
+                (Ok(CellState::ShowInvalidMine), _) | (Ok(CellState::ShowMineExploded), _) => mistakes += 1,
+                (Ok(CellState::Flagged), Ok(CellContent::Number(_))) if finished => mistakes += 1,
+                (Ok(CellState::AntiFlagged), Ok(content)) if finished && !content.is_anti_mine() => mistakes += 1,
+                _ => {}
+            }
+        }
+    }
+    mistakes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(rows: &[&str]) -> Board {
+        let template: Vec<Vec<char>> = rows.iter().map(|row| row.chars().collect()).collect();
+        let mut board = boardgenerator::create_position(&template, &mut thread_rng()).unwrap();
+        board.exclude_from_statistics();
+        board
+    }
+
+    #[test]
+    fn wrong_flags_only_count_once_the_game_is_over() {
+        let mut board = position(&["....", "....", "-*--", "----"]);
+        boardlogic::mark_board(0, 3, &mut board);
+        assert_eq!(count_mistakes(&board), 0);
+
+        boardlogic::poke_board(1, 2, &mut board);
+        assert_eq!(board.get_game_state(), GameState::GameOver);
+        assert_eq!(count_mistakes(&board), 2);
+    }
+}
@@ -1,9 +1,39 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+
 use serde::{Serialize, Deserialize};
+use serde_json::Value;
 
 use crate::GAME_SETTINGS;
+use crate::boardconfig::{self, BoardConfig, Topology};
+use crate::statistics;
+use crate::storage;
 
-#[derive(Serialize, Deserialize)]
-pub struct Settings {
+/// Name of the settings file inside the app config directory
+const SETTINGS_FILE_NAME: &str = "settings.json";
+
+/// Version of the settings file written by this build. Bump this whenever
+/// the layout of `SettingsFile` changes, and add a step to `migrate_settings`
+const SETTINGS_VERSION: u32 = 2;
+
+/// Full path of the settings file, set once the app config directory is known
+static SETTINGS_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// How mines are kept away from the first poked cell
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum GenerationMode {
+    /// The first poked cell and all cells around it are free of mines,
+    /// so the first poke always opens up an area
+    #[default]
+    SafeArea,
+    /// Only the first poked cell itself is free of mines
+    SafeCell
+}
+
+/// The toggles that change how the board reacts to the player
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Rules {
     /// Enable marking cells with a question mark. If disabled,
     /// cells will go back to 'unmarked' after clicking an already-flagged
     /// cell again
@@ -12,14 +42,14 @@ pub struct Settings {
     /// If true, the user can click an already uncovered cell with a number
     /// to uncover all surrounding cells
     quick_uncover: bool,
-    
+
     /// If true, won't uncover surrounding cells if the click isn't safe
     protected_quick_uncover: bool
 }
 
-impl Settings {
-    pub const fn new() -> Settings {
-        Settings {
+impl Rules {
+    pub const fn new() -> Rules {
+        Rules {
             questions_enabled: true,
             quick_uncover: true,
             protected_quick_uncover: true,
@@ -27,16 +57,325 @@ impl Settings {
     }
 }
 
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules::new()
+    }
+}
+
+/// A named bundle of a board configuration and the rules to play it with
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Profile {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub mines: u32,
+    #[serde(default)]
+    pub topology: Topology,
+    #[serde(default)]
+    pub generation_mode: GenerationMode,
+    #[serde(flatten)]
+    pub rules: Rules,
+    /// Built-in presets can't be renamed or deleted, and aren't saved to disk
+    #[serde(default, skip_deserializing)]
+    pub built_in: bool
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Settings {
+    #[serde(flatten)]
+    rules: Rules,
+
+    generation_mode: GenerationMode,
+
+    /// Name of the profile that was selected last, if any
+    active_profile: Option<String>,
+
+    /// The profiles created by the user. The built-in presets are not stored
+    profiles: Vec<Profile>
+}
+
+impl Settings {
+    pub const fn new() -> Settings {
+        Settings {
+            rules: Rules::new(),
+            generation_mode: GenerationMode::SafeArea,
+            active_profile: None,
+            profiles: Vec::new()
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings::new()
+    }
+}
+
+/// The layout of the settings file on disk
+#[derive(Serialize, Deserialize)]
+struct SettingsFile {
+    version: u32,
+    settings: Settings
+}
+
+/// The presets that ship with the game
+pub fn built_in_profiles() -> Vec<Profile> {
+    [("Beginner", 9, 9, 10), ("Intermediate", 16, 16, 40), ("Expert", 30, 16, 99)]
+        .into_iter()
+        .map(|(name, width, height, mines)| Profile {
+            name: String::from(name),
+            width,
+            height,
+            mines,
+            topology: Topology::Rectangle,
+            generation_mode: GenerationMode::SafeArea,
+            rules: Rules::new(),
+            built_in: true
+        })
+        .collect()
+}
+
 #[tauri::command]
-pub fn set_game_settings(settings: Settings) {
+/// Sets the rule toggles, and optionally the generation mode, used for
+/// the following games
+pub fn set_game_settings(settings: Rules, generation_mode: Option<GenerationMode>) {
     if let Ok(mut old_settings) = GAME_SETTINGS.lock() {
-        *old_settings = settings;
+        old_settings.rules = settings;
+        if let Some(mode) = generation_mode {
+            old_settings.generation_mode = mode;
+        }
+        save_settings(&old_settings);
+    }
+}
+
+#[tauri::command]
+/// Returns the settings currently used by the backend
+pub fn get_game_settings() -> Settings {
+    if let Ok(settings) = GAME_SETTINGS.lock() {
+        settings.clone()
+    } else {
+        println!("ERROR (s.ggs): Couldn't lock the global game settings object");
+        Settings::new()
+    }
+}
+
+#[tauri::command]
+/// Returns the built-in presets followed by the user's own profiles
+pub fn get_profiles() -> Vec<Profile> {
+    let mut profiles = built_in_profiles();
+    if let Ok(settings) = GAME_SETTINGS.lock() {
+        profiles.extend(settings.profiles.iter().cloned());
+    } else {
+        println!("ERROR (s.gp): Couldn't lock the global game settings object");
+    }
+    profiles
+}
+
+#[tauri::command]
+/// Saves a new user profile
+pub fn create_profile(profile: Profile) -> Result<(), String> {
+    let mut settings = GAME_SETTINGS.lock().map_err(|_| String::from("Couldn't lock the game settings"))?;
+    check_profile_name(&profile.name, &settings)?;
+    boardconfig::check_board_size(profile.width, profile.height, profile.mines, profile.generation_mode)?;
+
+    settings.profiles.push(Profile { built_in: false, ..profile });
+    save_settings(&settings);
+    Ok(())
+}
+
+#[tauri::command]
+/// Renames a user profile. Its statistics are moved to the new name
+pub fn rename_profile(old_name: String, new_name: String) -> Result<(), String> {
+    let mut settings = GAME_SETTINGS.lock().map_err(|_| String::from("Couldn't lock the game settings"))?;
+    check_profile_name(&new_name, &settings)?;
+    let profile = settings.profiles.iter_mut()
+        .find(|p| p.name == old_name)
+        .ok_or_else(|| format!("There is no user profile named '{}'", old_name))?;
+    profile.name = new_name.clone();
+
+    if settings.active_profile.as_deref() == Some(old_name.as_str()) {
+        settings.active_profile = Some(new_name.clone());
+    }
+    save_settings(&settings);
+    statistics::rename_preset(&old_name, &new_name);
+    Ok(())
+}
+
+#[tauri::command]
+/// Deletes a user profile. Its statistics are kept
+pub fn delete_profile(name: String) -> Result<(), String> {
+    let mut settings = GAME_SETTINGS.lock().map_err(|_| String::from("Couldn't lock the game settings"))?;
+    let count = settings.profiles.len();
+    settings.profiles.retain(|p| p.name != name);
+    if settings.profiles.len() == count {
+        return Err(format!("There is no user profile named '{}'", name));
+    }
+
+    if settings.active_profile.as_deref() == Some(name.as_str()) {
+        settings.active_profile = None;
+    }
+    save_settings(&settings);
+    Ok(())
+}
+
+#[tauri::command]
+/// Switches to the given profile: its rules and generation mode are used
+/// from now on. Returns the profile, so the board dimensions and mine count
+/// can be shown in the menu
+pub fn select_profile(name: String) -> Result<Profile, String> {
+    let mut settings = GAME_SETTINGS.lock().map_err(|_| String::from("Couldn't lock the game settings"))?;
+    let profile = built_in_profiles().into_iter()
+        .chain(settings.profiles.iter().cloned())
+        .find(|p| p.name == name)
+        .ok_or_else(|| format!("There is no profile named '{}'", name))?;
+
+    settings.rules = profile.rules.clone();
+    settings.generation_mode = profile.generation_mode;
+    settings.active_profile = Some(profile.name.clone());
+    save_settings(&settings);
+    Ok(profile)
+}
+
+/// Returns the name of the active profile if a board with the given config,
+/// played with the current settings, matches it exactly. Otherwise the game
+/// is counted as a custom game
+pub fn preset_name_for(config: &BoardConfig) -> String {
+    if let Ok(settings) = GAME_SETTINGS.lock() {
+        if let Some(active) = &settings.active_profile {
+            let matching = built_in_profiles().into_iter()
+                .chain(settings.profiles.iter().cloned())
+                .find(|p| &p.name == active)
+                .filter(|p| p.width == config.width() && p.height == config.height() && p.mines == config.mines()
+                    && p.topology == config.topology() && p.generation_mode == config.generation_mode()
+                    && p.rules == settings.rules && config.depth() == 1 && config.is_standard());
+            if let Some(profile) = matching {
+                return profile.name;
+            }
+        }
+    } else {
+        println!("ERROR (s.pnf): Couldn't lock the global game settings object");
+    }
+    String::from(statistics::CUSTOM_PRESET)
+}
+
+/// Checks that `name` can be used for a new or renamed user profile
+fn check_profile_name(name: &str, settings: &Settings) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err(String::from("A profile needs a name"));
+    }
+    if name == statistics::CUSTOM_PRESET || built_in_profiles().iter().any(|p| p.name == name) {
+        return Err(format!("'{}' is a reserved name", name));
+    }
+    if settings.profiles.iter().any(|p| p.name == name) {
+        return Err(format!("There already is a profile named '{}'", name));
+    }
+    Ok(())
+}
+
+/// Loads the settings file from `config_dir` into the global game settings,
+/// migrating it if it was written by an older version. If there is no
+/// settings file yet, the default settings are written to it. A file that
+/// can't be loaded is backed up before the defaults replace it
+pub fn load_settings(config_dir: Option<PathBuf>) {
+    let Some(config_dir) = config_dir else {
+        println!("ERROR (s.ls): No app config directory available, settings won't be saved");
+        return;
+    };
+    let path = config_dir.join(SETTINGS_FILE_NAME);
+
+    let loaded = storage::read_json_file(&path)
+        .and_then(|value| value.map(migrate_settings).transpose());
+    let settings = match loaded {
+        Ok(settings) => {
+            set_settings_path(Some(path));
+            // Writes the defaults on the first launch, and the migrated
+            // settings after an update
+            let settings = settings.unwrap_or_else(Settings::new);
+            save_settings(&settings);
+            settings
+        }
+        Err(e) => {
+            println!("ERROR (s.ls): Couldn't load settings file {}: {}", path.display(), e);
+            match storage::back_up_file(&path) {
+                Ok(backup) => {
+                    println!("ERROR (s.ls): Settings file backed up to {}, using the default settings", backup.display());
+                    set_settings_path(Some(path));
+                }
+                // Rather lose the changes of this session than the file
+                Err(e) => {
+                    println!("ERROR (s.ls): {}, settings won't be saved", e);
+                    set_settings_path(None);
+                }
+            }
+            Settings::new()
+        }
+    };
+
+    if let Ok(mut game_settings) = GAME_SETTINGS.lock() {
+        *game_settings = settings;
+    } else {
+        println!("ERROR (s.ls): Couldn't lock the global game settings object");
+    }
+}
+
+fn set_settings_path(path: Option<PathBuf>) {
+    if let Ok(mut settings_path) = SETTINGS_PATH.lock() {
+        *settings_path = path;
+    } else {
+        println!("ERROR (s.ssp): Couldn't lock the settings path");
+    }
+}
+
+/// Brings a settings file of any known version up to `SETTINGS_VERSION`
+fn migrate_settings(mut value: Value) -> Result<Settings, String> {
+    // Files without a version field are the plain settings object (version 0)
+    let mut version = value.get("version").and_then(Value::as_u64).unwrap_or(0);
+    if version > SETTINGS_VERSION as u64 {
+        return Err(format!("settings file version {} is newer than supported version {}", version, SETTINGS_VERSION));
+    }
+
+    if version == 0 {
+        value = serde_json::json!({ "version": 1, "settings": value });
+        version = 1;
+    }
+    if version == 1 {
+        // Version 2 added the generation mode and profiles, which all have
+        // defaults, so only the version number changes
+        value["version"] = Value::from(2);
+        version = 2;
+    }
+
+    debug_assert_eq!(version, SETTINGS_VERSION as u64);
+    serde_json::from_value::<SettingsFile>(value)
+        .map(|file| file.settings)
+        .map_err(|e| e.to_string())
+}
+
+/// Writes `settings` to the settings file, if its location is known
+fn save_settings(settings: &Settings) {
+    let path = match SETTINGS_PATH.lock() {
+        Ok(path) => path.clone(),
+        Err(_) => {
+            println!("ERROR (s.ss): Couldn't lock the settings path");
+            return;
+        }
+    };
+    let Some(path) = path else { return; };
+
+    let file = SettingsFile {
+        version: SETTINGS_VERSION,
+        settings: settings.clone()
+    };
+    if let Err(e) = storage::write_json_file(&path, &file) {
+        println!("ERROR (s.ss): {}", e);
     }
 }
 
 pub fn is_questions_enabled() -> bool {
     if let Ok(settings) = GAME_SETTINGS.lock() {
-        return settings.questions_enabled;
+        return settings.rules.questions_enabled;
     } else {
         println!("ERROR (s.iqe): Couldn't lock the global game settings object");
         return true;
@@ -45,7 +384,7 @@ pub fn is_questions_enabled() -> bool {
 
 pub fn is_quick_uncover_enabled() -> bool {
     if let Ok(settings) = GAME_SETTINGS.lock() {
-        return settings.quick_uncover;
+        return settings.rules.quick_uncover;
     } else {
         println!("ERROR (s.ique): Couldn't lock the global game settings object");
         return true;
@@ -54,9 +393,70 @@ pub fn is_quick_uncover_enabled() -> bool {
 
 pub fn is_protected_uncover_enabled() -> bool {
     if let Ok(settings) = GAME_SETTINGS.lock() {
-        return settings.protected_quick_uncover;
+        return settings.rules.protected_quick_uncover;
     } else {
         println!("ERROR (s.ipue): Couldn't lock the global game settings object");
         return true;
     }
-}
\ No newline at end of file
+}
+
+pub fn get_generation_mode() -> GenerationMode {
+    if let Ok(settings) = GAME_SETTINGS.lock() {
+        settings.generation_mode
+    } else {
+        println!("ERROR (s.ggm): Couldn't lock the global game settings object");
+        GenerationMode::SafeArea
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_0_is_migrated() {
+        let settings = migrate_settings(serde_json::json!({ "questions_enabled": false, "quick_uncover": true })).unwrap();
+        assert!(!settings.rules.questions_enabled);
+        assert!(settings.rules.quick_uncover);
+        assert!(settings.rules.protected_quick_uncover);
+        assert_eq!(settings.generation_mode, GenerationMode::SafeArea);
+        assert!(settings.profiles.is_empty());
+    }
+
+    #[test]
+    fn version_1_is_migrated() {
+        let settings = migrate_settings(serde_json::json!({
+            "version": 1,
+            "settings": { "questions_enabled": true, "quick_uncover": false, "protected_quick_uncover": false }
+        })).unwrap();
+        assert!(settings.rules.questions_enabled);
+        assert!(!settings.rules.quick_uncover);
+        assert!(!settings.rules.protected_quick_uncover);
+        assert_eq!(settings.active_profile, None);
+    }
+
+    #[test]
+    fn version_2_is_read_as_is() {
+        let settings = migrate_settings(serde_json::json!({
+            "version": 2,
+            "settings": {
+                "questions_enabled": false,
+                "generation_mode": "SafeCell",
+                "active_profile": "Small",
+                "profiles": [{ "name": "Small", "width": 5, "height": 5, "mines": 3 }]
+            }
+        })).unwrap();
+        assert!(!settings.rules.questions_enabled);
+        assert_eq!(settings.generation_mode, GenerationMode::SafeCell);
+        assert_eq!(settings.active_profile.as_deref(), Some("Small"));
+        assert_eq!(settings.profiles.len(), 1);
+        assert_eq!(settings.profiles[0].generation_mode, GenerationMode::SafeArea);
+        assert!(settings.profiles[0].rules == Rules::new());
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        let newer = serde_json::json!({ "version": SETTINGS_VERSION + 1, "settings": {} });
+        assert!(migrate_settings(newer).is_err());
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::BTreeMap;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use serde::Serialize;
+
+use crate::board::GameState;
+use crate::boardconfig::BoardConfig;
+use crate::boardgenerator;
+use crate::boardlogic;
+use crate::bot;
+use crate::difficulty;
+use crate::hint::HintKind;
+use crate::settings::GenerationMode;
+
+/// Number of games that are simulated, if no number is given
+const DEFAULT_GAMES: u32 = 1000;
+
+/// Most games a single simulation may run
+const MAX_GAMES: u32 = 100_000;
+
+/// What the bot achieved on many boards of one configuration
+#[derive(Serialize, Clone, Debug)]
+pub struct SimulationReport {
+    games: u32,
+    wins: u32,
+    win_probability: f64,
+    /// Guesses the bot made per game, counting the guess a lost game ended on
+    average_guesses: f64,
+    average_bbbv: f64,
+    /// How many boards had each 3BV
+    bbbv_counts: BTreeMap<u32, u32>
+}
+
+impl SimulationReport {
+    /// The lowest 3BV that at least `fraction` of the boards are at or below
+    fn bbbv_percentile(&self, fraction: f64) -> u32 {
+        let target = (self.games as f64 * fraction).ceil().max(1.0) as u32;
+        let mut seen = 0;
+        for (bbbv, count) in &self.bbbv_counts {
+            seen += count;
+            if seen >= target {
+                return *bbbv;
+            }
+        }
+        0
+    }
+}
+
+#[tauri::command(async)]
+/// Lets the bot play `games` boards of the given size, each started from
+/// the middle, to tell how likely a configuration is to be won without
+/// luck. `seed` makes the boards the same on every run. Runs off the main
+/// thread, as many games take a while
+pub fn simulate_games(width: u32, height: u32, depth: Option<u32>, mines: u32, generation_mode: Option<GenerationMode>,
+        games: Option<u32>, seed: Option<u64>) -> Result<SimulationReport, String> {
+    let depth = depth.unwrap_or(1);
+    let config = BoardConfig::new_layered(width, height, depth, mines, width / 2, depth / 2 * height + height / 2,
+        generation_mode.unwrap_or_default())?;
+    let games = games.unwrap_or(DEFAULT_GAMES);
+    if games == 0 || games > MAX_GAMES {
+        return Err(format!("Between 1 and {} games can be simulated", MAX_GAMES));
+    }
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy()
+    };
+    Ok(simulate(&config, games, &mut rng))
+}
+
+/// Runs a simulation from the command line and prints the report. Run with
+/// `--simulate WIDTH HEIGHT MINES [GAMES] [safe-cell]`, from a debug build on Windows
+pub fn run(args: &[String]) {
+    let numbers: Vec<u32> = args.iter().map_while(|arg| arg.parse().ok()).collect();
+    let generation_mode = if args.iter().any(|arg| arg == "safe-cell") { GenerationMode::SafeCell } else { GenerationMode::SafeArea };
+    let (width, height, mines, games) = match numbers[..] {
+        [width, height, mines] => (width, height, mines, None),
+        [width, height, mines, games, ..] => (width, height, mines, Some(games)),
+        _ => {
+            println!("ERROR (sim.run): Expected --simulate WIDTH HEIGHT MINES [GAMES] [safe-cell]");
+            return;
+        }
+    };
+
+    println!("Simulating {} games of {}x{} with {} mines ({:?})", games.unwrap_or(DEFAULT_GAMES), width, height, mines, generation_mode);
+    let report = match simulate_games(width, height, None, mines, Some(generation_mode), games, None) {
+        Ok(report) => report,
+        Err(e) => {
+            println!("ERROR (sim.run): {}", e);
+            return;
+        }
+    };
+    println!("Won {} of {} games ({:.1}%)", report.wins, report.games, report.win_probability * 100.0);
+    println!("Guesses per game: {:.2}", report.average_guesses);
+    println!("3BV: average {:.1}, min {}, 10% {}, median {}, 90% {}, max {}", report.average_bbbv,
+        report.bbbv_percentile(0.0), report.bbbv_percentile(0.1), report.bbbv_percentile(0.5),
+        report.bbbv_percentile(0.9), report.bbbv_percentile(1.0));
+}
+
+/// Lets the bot play `games` boards made from `config`, after poking the
+/// first cell of the config
+fn simulate(config: &BoardConfig, games: u32, rng: &mut StdRng) -> SimulationReport {
+    let mut wins = 0;
+    let mut guesses = 0;
+    let mut bbbv_total = 0;
+    let mut bbbv_counts = BTreeMap::new();
+
+    for _ in 0..games {
+        let mut board = boardgenerator::create_board(config, rng);
+        let (bbbv, _) = difficulty::count_clicks(&board);
+        bbbv_total += bbbv as u64;
+        *bbbv_counts.entry(bbbv).or_insert(0) += 1;
+
+        // Simulated games never count, even if the first poke ends them
+        board.exclude_from_statistics();
+        boardlogic::poke_board(config.first_x() as usize, config.first_y() as usize, &mut board);
+        while let Ok((hint, _)) = bot::step(&mut board) {
+            if hint.kind == HintKind::Guess {
+                guesses += 1;
+            }
+        }
+        if board.get_game_state() == GameState::GameWon {
+            wins += 1;
+        }
+    }
+
+    SimulationReport {
+        games,
+        wins,
+        win_probability: wins as f64 / games as f64,
+        average_guesses: guesses as f64 / games as f64,
+        average_bbbv: bbbv_total as f64 / games as f64,
+        bbbv_counts
+    }
+}
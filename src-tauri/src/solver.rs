@@ -0,0 +1,711 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use serde::{Serialize, Deserialize};
+
+use crate::board::{Board, CellContent, CellState, Clue, MAX_NEIGHBOURS};
+
+/// Largest number of partial layouts tried when enumerating one group of
+/// cells. Groups that need more are left alone, so a huge frontier can't
+/// stall the solver
+const ENUMERATION_LIMIT: usize = 20_000;
+
+/// What the solver knows about a cell
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Knowledge {
+    Unknown,
+    Mine,
+    /// A cell that is proven to be safe, but isn't uncovered yet
+    Safe,
+    /// An uncovered cell showing the number of mines around it, or one more
+    /// or one less than that on a liar board
+    Clue(u8),
+    /// An uncovered cell showing a range or parity instead of its number
+    Fuzzy(Clue)
+}
+
+/// The ways the solver finds safe cells and mines, from easiest to hardest
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Technique {
+    /// A number that already touches as many mines, or as many hidden
+    /// cells, as it shows
+    Single,
+    /// Two numbers that share some of their hidden cells
+    Pair,
+    /// Trying every layout of mines around a group of numbers, or counting
+    /// the mines that are left
+    Enumeration
+}
+
+/// Cells the solver proved to be safe or mines, and how
+pub struct Deduction {
+    pub safe: Vec<(usize, usize)>,
+    pub mines: Vec<(usize, usize)>,
+    pub technique: Technique
+}
+
+/// A number on the board: the hidden `cells` around it hold one of the
+/// counts in `mines`, which is a single count unless the number may lie
+struct Constraint {
+    cells: Vec<usize>,
+    mines: Vec<usize>
+}
+
+/// Every valid layout of mines in a group of cells that share numbers
+struct Enumeration {
+    cells: Vec<usize>,
+    /// The layouts, by the number of mines in them
+    layouts: BTreeMap<usize, Layouts>
+}
+
+/// The layouts of a group with the same number of mines
+#[derive(Clone)]
+struct Layouts {
+    /// Number of layouts
+    solutions: usize,
+    /// Per cell, the number of layouts with a mine in it
+    mine_counts: Vec<usize>
+}
+
+/// Solves a board the way a player would, only from the uncovered numbers
+/// and the total mine count. The board is only used for its shape, and for
+/// whether its numbers lie
+#[derive(Clone)]
+pub struct Solver<'a> {
+    board: &'a Board,
+    /// Every number is one more or one less than the mines around it
+    liar: bool,
+    /// What is known about each cell, by `y * width + x`
+    knowledge: Vec<Knowledge>,
+    /// Per cell, the number of unknown cells around it, so numbers that
+    /// are done can be skipped without looking at their neighbours
+    unknown_neighbours: Vec<usize>,
+    unknown_count: usize,
+    mines: usize,
+    found_mines: usize
+}
+
+impl<'a> Solver<'a> {
+    /// A solver that knows nothing about `board` yet
+    pub fn new(board: &'a Board) -> Solver<'a> {
+        let width = board.get_width();
+        let cell_count = width * board.get_height();
+        Solver {
+            board,
+            liar: board.is_liar(),
+            knowledge: vec![Knowledge::Unknown; cell_count],
+            unknown_neighbours: (0..cell_count).map(|cell| board.neighbours(cell % width, cell / width).len()).collect(),
+            unknown_count: cell_count,
+            mines: board.get_mine_total(),
+            found_mines: 0
+        }
+    }
+
+    /// A solver that knows what the player sees on `board`: its uncovered
+    /// numbers, and its flags, which are trusted to be mines
+    pub fn from_visible(board: &'a Board) -> Solver<'a> {
+        let mut solver = Solver::new(board);
+        for y in 0..board.get_height() {
+            for x in 0..board.get_width() {
+                match (board.get_state(x, y), board.get_shown_content(x, y)) {
+                    (Ok(CellState::ShowValue), Ok(CellContent::Number(value))) => match board.get_clue(x, y) {
+                        Some(clue) => solver.reveal_clue(x, y, clue),
+                        None => solver.reveal(x, y, value as u8)
+                    },
+                    (Ok(CellState::Flagged), _) => solver.mark_mine(x, y),
+                    _ => {}
+                }
+            }
+        }
+        solver
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Knowledge {
+        self.knowledge[self.index(x, y)]
+    }
+
+    /// Records that `(x, y)` is uncovered and shows `value`
+    pub fn reveal(&mut self, x: usize, y: usize, value: u8) {
+        self.set(x, y, Knowledge::Clue(value));
+    }
+
+    /// Records that `(x, y)` is uncovered and shows `clue` instead of its number
+    pub fn reveal_clue(&mut self, x: usize, y: usize, clue: Clue) {
+        self.set(x, y, Knowledge::Fuzzy(clue));
+    }
+
+    /// Records that `(x, y)` is a mine
+    pub fn mark_mine(&mut self, x: usize, y: usize) {
+        self.set(x, y, Knowledge::Mine);
+    }
+
+    /// Records that `(x, y)` is safe, without knowing its number
+    pub fn mark_safe(&mut self, x: usize, y: usize) {
+        self.set(x, y, Knowledge::Safe);
+    }
+
+    /// Number of cells that are neither uncovered nor known to be mines
+    pub fn unknown_count(&self) -> usize {
+        self.unknown_count
+    }
+
+    /// Number of mines that aren't known yet
+    pub fn remaining_mines(&self) -> usize {
+        self.mines.saturating_sub(self.found_mines)
+    }
+
+    /// Finds safe cells and mines with the easiest technique that finds any.
+    /// Returns `None` if the board can't be solved further without guessing
+    pub fn deduce(&self) -> Option<Deduction> {
+        self.deduce_up_to(Technique::Enumeration)
+    }
+
+    /// Like `deduce`, but only with techniques up to `hardest`
+    pub fn deduce_up_to(&self, hardest: Technique) -> Option<Deduction> {
+        let constraints = self.constraints();
+        let mut deduction = self.deduce_singles(&constraints);
+        if deduction.is_none() && hardest >= Technique::Pair {
+            deduction = self.deduce_pairs(&constraints);
+        }
+        if deduction.is_none() && hardest >= Technique::Enumeration {
+            deduction = self.deduce_by_enumeration(&constraints);
+        }
+        deduction
+    }
+
+    /// Every cell that can be proven safe or a mine from what is known now,
+    /// with techniques up to `hardest`. Nothing new is uncovered, so a cell
+    /// that is proven safe only helps by not being a mine. The technique of
+    /// the result is the hardest one that was needed
+    pub fn forced_cells(&self, hardest: Technique) -> Deduction {
+        let mut solver = self.clone();
+        let mut forced = Deduction { safe: Vec::new(), mines: Vec::new(), technique: Technique::Single };
+        while let Some(deduction) = solver.deduce_up_to(hardest) {
+            for (x, y) in &deduction.safe {
+                solver.mark_safe(*x, *y);
+            }
+            for (x, y) in &deduction.mines {
+                solver.mark_mine(*x, *y);
+            }
+            forced.safe.extend(deduction.safe);
+            forced.mines.extend(deduction.mines);
+            forced.technique = forced.technique.max(deduction.technique);
+        }
+        forced.safe.sort_unstable();
+        forced.mines.sort_unstable();
+        forced
+    }
+
+    /// The chance that each unknown cell is a mine, as `(x, y, probability)`
+    pub fn mine_probabilities(&self) -> Vec<(usize, usize, f64)> {
+        let constraints = self.constraints();
+        let remaining = self.remaining_mines();
+        let groups: Vec<Enumeration> = self.enumerate_groups(&constraints).into_iter().flatten().collect();
+        // Cells of groups that hit the enumeration limit count as cells away
+        // from the numbers, like in `deduce_by_enumeration`
+        let other_cells = self.unknown_count() - groups.iter().map(|g| g.cells.len()).sum::<usize>();
+        let weights = off_frontier_weights(other_cells, remaining);
+        let weight = |frontier_mines: usize| weights.get(frontier_mines).copied().unwrap_or(0.0);
+
+        // A layout of a group is as likely as the number of ways the other
+        // groups and the cells away from the numbers can hold the rest
+        let mut probabilities: Vec<Option<f64>> = vec![None; self.knowledge.len()];
+        for (g, group) in groups.iter().enumerate() {
+            let others = mine_distribution(groups.iter().enumerate().filter(|(i, _)| *i != g).map(|(_, other)| other));
+            let mut total = 0.0;
+            let mut mine_weights = vec![0.0; group.cells.len()];
+            for (mines, layouts) in &group.layouts {
+                let rest: f64 = others.iter().enumerate().map(|(other_mines, ways)| ways * weight(mines + other_mines)).sum();
+                total += layouts.solutions as f64 * rest;
+                for (mine_weight, count) in mine_weights.iter_mut().zip(&layouts.mine_counts) {
+                    *mine_weight += *count as f64 * rest;
+                }
+            }
+            if total > 0.0 {
+                for (cell, mine_weight) in group.cells.iter().zip(&mine_weights) {
+                    probabilities[*cell] = Some(mine_weight / total);
+                }
+            }
+        }
+
+        // Cells away from the numbers share the mines that are expected to be left
+        let mut total = 0.0;
+        let mut expected_left = 0.0;
+        for (frontier_mines, ways) in mine_distribution(groups.iter()).into_iter().enumerate() {
+            let ways = ways * weight(frontier_mines);
+            total += ways;
+            expected_left += ways * remaining.saturating_sub(frontier_mines) as f64;
+        }
+        let other_probability = if other_cells == 0 || total == 0.0 {
+            0.0
+        } else {
+            (expected_left / total / other_cells as f64).clamp(0.0, 1.0)
+        };
+
+        self.unknown_cells()
+            .map(|cell| {
+                let (x, y) = self.position(cell);
+                (x, y, probabilities[cell].unwrap_or(other_probability))
+            })
+            .collect()
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        y * self.board.get_width() + x
+    }
+
+    fn position(&self, index: usize) -> (usize, usize) {
+        (index % self.board.get_width(), index / self.board.get_width())
+    }
+
+    fn unknown_cells(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.knowledge.len()).filter(|cell| self.knowledge[*cell] == Knowledge::Unknown)
+    }
+
+    fn set(&mut self, x: usize, y: usize, knowledge: Knowledge) {
+        let index = self.index(x, y);
+        match (self.knowledge[index], knowledge) {
+            (Knowledge::Unknown, Knowledge::Unknown) => {},
+            // A safe cell can still be uncovered
+            (Knowledge::Safe, Knowledge::Clue(_)) | (Knowledge::Safe, Knowledge::Fuzzy(_)) => {},
+            (Knowledge::Unknown, _) => {
+                self.unknown_count -= 1;
+                for (nx, ny) in self.board.neighbours(x, y) {
+                    let neighbour = self.index(nx, ny);
+                    self.unknown_neighbours[neighbour] -= 1;
+                }
+            },
+            // Cells only ever become known
+            _ => return
+        }
+        if knowledge == Knowledge::Mine {
+            self.found_mines += 1;
+        }
+        self.knowledge[index] = knowledge;
+    }
+
+    /// One constraint for every number that still touches unknown cells. A
+    /// number that contradicts what is known has no counts left, and is left out
+    fn constraints(&self) -> Vec<Constraint> {
+        let mut constraints = Vec::new();
+        for (cell, knowledge) in self.knowledge.iter().enumerate() {
+            if self.unknown_neighbours[cell] == 0 { continue; }
+            let counts: Vec<usize> = match knowledge {
+                Knowledge::Clue(value) if self.liar => {
+                    let value = *value as usize;
+                    [value.checked_sub(1), Some(value + 1)].into_iter().flatten().collect()
+                },
+                Knowledge::Clue(value) => vec![*value as usize],
+                Knowledge::Fuzzy(clue) => (0..=MAX_NEIGHBOURS).filter(|count| clue.fits(*count as u8)).collect(),
+                _ => continue
+            };
+            let (x, y) = self.position(cell);
+            let mut cells = Vec::new();
+            let mut known_mines = 0;
+            for (nx, ny) in self.board.neighbours(x, y) {
+                let neighbour = self.index(nx, ny);
+                match self.knowledge[neighbour] {
+                    Knowledge::Unknown => cells.push(neighbour),
+                    Knowledge::Mine => known_mines += 1,
+                    Knowledge::Safe | Knowledge::Clue(_) | Knowledge::Fuzzy(_) => {}
+                }
+            }
+            if cells.is_empty() { continue; }
+            cells.sort_unstable();
+            cells.dedup();
+
+            let mines: Vec<usize> = counts.into_iter()
+                .filter(|count| *count >= known_mines && *count - known_mines <= cells.len())
+                .map(|count| count - known_mines)
+                .collect();
+            if !mines.is_empty() {
+                constraints.push(Constraint { cells, mines });
+            }
+        }
+        constraints
+    }
+
+    fn deduce_singles(&self, constraints: &[Constraint]) -> Option<Deduction> {
+        let mut safe = Vec::new();
+        let mut mines = Vec::new();
+        for constraint in constraints {
+            if constraint.mines.iter().all(|count| *count == 0) {
+                safe.extend(&constraint.cells);
+            } else if constraint.mines.iter().all(|count| *count == constraint.cells.len()) {
+                mines.extend(&constraint.cells);
+            }
+        }
+        self.deduction(safe, mines, Technique::Single)
+    }
+
+    /// Compares every two numbers that share a cell. If the cells only the
+    /// second one touches must hold all of its extra mines, those are mines
+    /// and the cells only the first one touches are safe. Only numbers with a
+    /// single count are compared
+    fn deduce_pairs(&self, constraints: &[Constraint]) -> Option<Deduction> {
+        let mut constraints_of_cell: Vec<Vec<usize>> = vec![Vec::new(); self.knowledge.len()];
+        for (i, constraint) in constraints.iter().enumerate() {
+            for cell in &constraint.cells {
+                constraints_of_cell[*cell].push(i);
+            }
+        }
+
+        let mut safe = Vec::new();
+        let mut mines = Vec::new();
+        for (a_index, a) in constraints.iter().enumerate() {
+            let mut partners: Vec<usize> = a.cells.iter()
+                .flat_map(|cell| constraints_of_cell[*cell].iter().copied())
+                .filter(|b_index| *b_index != a_index)
+                .collect();
+            partners.sort_unstable();
+            partners.dedup();
+
+            let [a_mines] = a.mines[..] else { continue; };
+            for b in partners.into_iter().map(|b_index| &constraints[b_index]) {
+                let [b_mines] = b.mines[..] else { continue; };
+                let only_a: Vec<usize> = a.cells.iter().copied().filter(|cell| !b.cells.contains(cell)).collect();
+                let only_b: Vec<usize> = b.cells.iter().copied().filter(|cell| !a.cells.contains(cell)).collect();
+                if b_mines >= a_mines && b_mines - a_mines == only_b.len() && !(only_a.is_empty() && only_b.is_empty()) {
+                    safe.extend(&only_a);
+                    mines.extend(&only_b);
+                }
+            }
+        }
+        self.deduction(safe, mines, Technique::Pair)
+    }
+
+    /// Enumerates every group of numbers, and combines the groups with the
+    /// number of mines that are left. The mines of a group can be anything
+    /// from its fewest to its most, as long as the other groups and the
+    /// cells away from the numbers can hold the rest
+    fn deduce_by_enumeration(&self, constraints: &[Constraint]) -> Option<Deduction> {
+        let groups: Vec<Enumeration> = self.enumerate_groups(constraints).into_iter().flatten().collect();
+        let remaining = self.remaining_mines();
+        // Cells of groups that hit the enumeration limit count as free cells,
+        // which can hold any number of mines
+        let free = self.unknown_count() - groups.iter().map(|g| g.cells.len()).sum::<usize>();
+        let fewest: usize = groups.iter().map(|g| g.fewest_mines()).sum();
+        let most: usize = groups.iter().map(|g| g.most_mines()).sum();
+
+        let mut safe = Vec::new();
+        let mut mines = Vec::new();
+        for group in &groups {
+            let others_fewest = fewest - group.fewest_mines();
+            let others_most = most - group.most_mines() + free;
+            let possible: Vec<&Layouts> = group.layouts.iter()
+                .filter(|(count, _)| **count + others_fewest <= remaining && **count + others_most >= remaining)
+                .map(|(_, layouts)| layouts)
+                .collect();
+            if possible.is_empty() { continue; }
+
+            for (i, cell) in group.cells.iter().enumerate() {
+                if possible.iter().all(|layouts| layouts.mine_counts[i] == 0) {
+                    safe.push(*cell);
+                } else if possible.iter().all(|layouts| layouts.mine_counts[i] == layouts.solutions) {
+                    mines.push(*cell);
+                }
+            }
+        }
+
+        // The free cells hold whatever mines the groups leave
+        if free > 0 {
+            let free_most = remaining.saturating_sub(fewest).min(free);
+            let free_fewest = remaining.saturating_sub(most);
+            let mut in_group = vec![false; self.knowledge.len()];
+            for cell in groups.iter().flat_map(|g| &g.cells) {
+                in_group[*cell] = true;
+            }
+            let free_cells = self.unknown_cells().filter(|cell| !in_group[*cell]);
+            if free_most == 0 {
+                safe.extend(free_cells);
+            } else if free_fewest >= free {
+                mines.extend(free_cells);
+            }
+        }
+        self.deduction(safe, mines, Technique::Enumeration)
+    }
+
+    fn deduction(&self, mut safe: Vec<usize>, mut mines: Vec<usize>, technique: Technique) -> Option<Deduction> {
+        if safe.is_empty() && mines.is_empty() {
+            return None;
+        }
+        safe.sort_unstable();
+        safe.dedup();
+        mines.sort_unstable();
+        mines.dedup();
+        Some(Deduction {
+            safe: safe.into_iter().map(|cell| self.position(cell)).collect(),
+            mines: mines.into_iter().map(|cell| self.position(cell)).collect(),
+            technique
+        })
+    }
+
+    /// Splits the constraints into groups that share cells, and enumerates
+    /// each group. Groups that hit the enumeration limit are `None`
+    fn enumerate_groups(&self, constraints: &[Constraint]) -> Vec<Option<Enumeration>> {
+        let mut constraints_of_cell: Vec<Vec<usize>> = vec![Vec::new(); self.knowledge.len()];
+        for (i, constraint) in constraints.iter().enumerate() {
+            for cell in &constraint.cells {
+                constraints_of_cell[*cell].push(i);
+            }
+        }
+
+        let remaining = self.remaining_mines();
+        let mut visited = vec![false; constraints.len()];
+        let mut in_group = vec![false; self.knowledge.len()];
+        let mut groups = Vec::new();
+        for start in 0..constraints.len() {
+            if visited[start] { continue; }
+
+            // Collect the group breadth-first, so neighbouring cells are
+            // enumerated one after another and layouts fail early
+            let mut group = Vec::new();
+            let mut cells = Vec::new();
+            let mut queue = VecDeque::from([start]);
+            visited[start] = true;
+            while let Some(i) = queue.pop_front() {
+                group.push(i);
+                for cell in &constraints[i].cells {
+                    if !in_group[*cell] {
+                        in_group[*cell] = true;
+                        cells.push(*cell);
+                    }
+                    for other in &constraints_of_cell[*cell] {
+                        if !visited[*other] {
+                            visited[*other] = true;
+                            queue.push_back(*other);
+                        }
+                    }
+                }
+            }
+            groups.push(enumerate(&cells, &group.iter().map(|i| &constraints[*i]).collect::<Vec<_>>(), remaining));
+        }
+        groups
+    }
+}
+
+/// Per number of mines in `groups` together, the number of layouts of all
+/// of them with that many mines, relative to the most common number
+fn mine_distribution<'b>(groups: impl Iterator<Item = &'b Enumeration>) -> Vec<f64> {
+    let mut distribution = vec![1.0];
+    for group in groups {
+        let mut next = vec![0.0; distribution.len() + group.most_mines()];
+        for (mines, ways) in distribution.iter().enumerate() {
+            for (group_mines, layouts) in &group.layouts {
+                next[mines + group_mines] += ways * layouts.solutions as f64;
+            }
+        }
+        // Only the ratios matter, and the counts multiply quickly
+        let most = next.iter().copied().fold(0.0, f64::max);
+        distribution = next.into_iter().map(|ways| ways / most).collect();
+    }
+    distribution
+}
+
+/// Per number of mines on the numbers' cells, the number of ways to place
+/// the rest of the `remaining` mines in the `other` cells away from them,
+/// which is `C(other, remaining - mines)`, relative to the largest
+fn off_frontier_weights(other: usize, remaining: usize) -> Vec<f64> {
+    // Worked out as logarithms from the fewest mines on the numbers up,
+    // using C(n, r - 1) = C(n, r) * r / (n - r + 1)
+    let first = remaining.saturating_sub(other);
+    let mut logs = vec![f64::NEG_INFINITY; remaining + 1];
+    let mut log = 0.0;
+    logs[first] = log;
+    for (mines, entry) in logs.iter_mut().enumerate().skip(first + 1) {
+        let left = (remaining - mines + 1) as f64;
+        log += left.ln() - (other as f64 - left + 1.0).ln();
+        *entry = log;
+    }
+    let largest = logs.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    logs.into_iter().map(|log| (log - largest).exp()).collect()
+}
+
+/// Tries every layout of at most `max_mines` mines in `cells` that fits all `constraints`
+fn enumerate(cells: &[usize], constraints: &[&Constraint], max_mines: usize) -> Option<Enumeration> {
+    // Per cell, the constraints it is part of
+    let local: HashMap<usize, usize> = cells.iter().enumerate().map(|(i, cell)| (*cell, i)).collect();
+    let mut cell_constraints: Vec<Vec<usize>> = vec![Vec::new(); cells.len()];
+    for (c, constraint) in constraints.iter().enumerate() {
+        for cell in &constraint.cells {
+            cell_constraints[local[cell]].push(c);
+        }
+    }
+
+    let mut search = Search {
+        cell_constraints: &cell_constraints,
+        required: constraints.iter().map(|c| c.mines.as_slice()).collect(),
+        placed: vec![0; constraints.len()],
+        open: constraints.iter().map(|c| c.cells.len()).collect(),
+        layout: vec![false; cells.len()],
+        max_mines,
+        steps: 0,
+        layouts: BTreeMap::new()
+    };
+    if !search.run(0, 0) {
+        return None;
+    }
+    if search.layouts.is_empty() {
+        println!("ERROR (so.e): The numbers around a group of {} cells contradict each other", cells.len());
+        return None;
+    }
+    Some(Enumeration { cells: cells.to_vec(), layouts: search.layouts })
+}
+
+/// State of the backtracking search in `enumerate`
+struct Search<'a> {
+    cell_constraints: &'a [Vec<usize>],
+    /// Per constraint, the mine counts it allows, the mines it has and the
+    /// cells it has left
+    required: Vec<&'a [usize]>,
+    placed: Vec<usize>,
+    open: Vec<usize>,
+    layout: Vec<bool>,
+    max_mines: usize,
+    steps: usize,
+    layouts: BTreeMap<usize, Layouts>
+}
+
+impl Enumeration {
+    fn fewest_mines(&self) -> usize {
+        self.layouts.keys().next().copied().unwrap_or(0)
+    }
+
+    fn most_mines(&self) -> usize {
+        self.layouts.keys().next_back().copied().unwrap_or(0)
+    }
+}
+
+impl Search<'_> {
+    /// Decides cell `cell` and the ones after it. Returns false once the
+    /// enumeration limit is hit
+    fn run(&mut self, cell: usize, mines: usize) -> bool {
+        self.steps += 1;
+        if self.steps > ENUMERATION_LIMIT {
+            return false;
+        }
+        if cell == self.layout.len() {
+            let cell_count = self.layout.len();
+            let layouts = self.layouts.entry(mines)
+                .or_insert_with(|| Layouts { solutions: 0, mine_counts: vec![0; cell_count] });
+            layouts.solutions += 1;
+            for (count, mine) in layouts.mine_counts.iter_mut().zip(&self.layout) {
+                *count += *mine as usize;
+            }
+            return true;
+        }
+
+        for mine in [false, true] {
+            if mine && mines == self.max_mines { continue; }
+            if !self.fits(cell, mine) { continue; }
+
+            self.place(cell, mine, true);
+            let finished = self.run(cell + 1, mines + mine as usize);
+            self.place(cell, mine, false);
+            if !finished {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// True if every constraint of `cell` can still be met with `mine` in it
+    fn fits(&self, cell: usize, mine: bool) -> bool {
+        self.cell_constraints[cell].iter().all(|c| {
+            let placed = self.placed[*c] + mine as usize;
+            // `open` still counts this cell
+            self.required[*c].iter().any(|required| placed <= *required && placed + self.open[*c] > *required)
+        })
+    }
+
+    fn place(&mut self, cell: usize, mine: bool, add: bool) {
+        self.layout[cell] = mine && add;
+        for c in &self.cell_constraints[cell] {
+            if add {
+                self.open[*c] -= 1;
+                self.placed[*c] += mine as usize;
+            } else {
+                self.open[*c] += 1;
+                self.placed[*c] -= mine as usize;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boardconfig::BoardConfig;
+    use crate::settings::GenerationMode;
+
+    fn board(mines: u32) -> Board {
+        Board::new(&BoardConfig::new(4, 4, mines, 0, 0, GenerationMode::SafeCell).unwrap())
+    }
+
+    /// Uncovers the cells of `rows` that show a digit, from the top. Other
+    /// characters stay unknown
+    fn reveal_rows(solver: &mut Solver, rows: &[&str]) {
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                if let Some(value) = c.to_digit(10) {
+                    solver.reveal(x, y, value as u8);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pair_finds_the_mines_of_a_1_2_2_1() {
+        let board = board(2);
+        let mut solver = Solver::new(&board);
+        reveal_rows(&mut solver, &["....", "1221", "0000", "0000"]);
+
+        assert!(solver.deduce_up_to(Technique::Single).is_none());
+        let deduction = solver.deduce().unwrap();
+        assert_eq!(deduction.technique, Technique::Pair);
+        assert!(deduction.mines.contains(&(1, 0)));
+        assert!(deduction.mines.contains(&(2, 0)));
+        assert!(deduction.safe.iter().all(|cell| [(0, 0), (3, 0)].contains(cell)));
+    }
+
+    #[test]
+    fn enumeration_counts_the_mines_that_are_left() {
+        // Four ones share two mines, which only works if each mine touches two of them
+        let board = board(2);
+        let mut solver = Solver::new(&board);
+        reveal_rows(&mut solver, &["....", ".1.1", "....", "1.1."]);
+
+        assert!(solver.deduce_up_to(Technique::Pair).is_none());
+        let deduction = solver.deduce().unwrap();
+        assert_eq!(deduction.technique, Technique::Enumeration);
+        let mut safe = deduction.safe.clone();
+        safe.sort_unstable();
+        assert_eq!(safe, vec![(0, 0), (0, 1), (1, 0), (2, 2), (3, 3)]);
+    }
+
+    #[test]
+    fn probabilities_weigh_layouts_by_the_mines_left_elsewhere() {
+        // The top row holds the middle cell's mine, or the mines of both
+        // corners. With 2 mines, a single mine there leaves 4 ways to place
+        // the other one in the bottom row, and two mines leave only 1 way
+        let board = board(2);
+        let mut solver = Solver::new(&board);
+        for x in 0..4 {
+            for y in 1..3 {
+                if (x, y) != (1, 1) && (x, y) != (2, 1) {
+                    solver.mark_safe(x, y);
+                }
+            }
+        }
+        reveal_rows(&mut solver, &["....", ".11."]);
+
+        let probabilities: HashMap<(usize, usize), f64> = solver.mine_probabilities().into_iter()
+            .map(|(x, y, probability)| ((x, y), probability))
+            .collect();
+        let close = |cell, expected: f64| (probabilities[&cell] - expected).abs() < 1e-9;
+        assert!(close((0, 0), 1.0 / 9.0));
+        assert!(close((1, 0), 4.0 / 9.0));
+        assert!(close((2, 0), 4.0 / 9.0));
+        assert!(close((3, 0), 1.0 / 9.0));
+        assert!(close((0, 3), 2.0 / 9.0));
+    }
+}
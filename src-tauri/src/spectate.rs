@@ -0,0 +1,249 @@
+use std::collections::VecDeque;
+use std::net::{Shutdown, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::board::{Board, CellDetails};
+use crate::boardlogic::GameChange;
+use crate::network;
+
+/// The broadcast of the local game to spectators, if it's running
+static BROADCAST: Mutex<Option<Broadcast>> = Mutex::new(None);
+
+/// The game of another instance that is being watched, if any
+static WATCH: Mutex<Option<Watch>> = Mutex::new(None);
+
+/// How often the broadcast checks for changes whose delay has passed
+const RELEASE_INTERVAL: Duration = Duration::from_millis(20);
+
+/// What spectators can see of a game. It never contains the value of a
+/// cell that isn't uncovered
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SpectatorView {
+    /// The counters and game state of the last change
+    status: GameChange,
+    cells: Vec<Vec<CellDetails>>
+}
+
+/// The messages sent to spectators, one JSON object per line
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type")]
+enum SpectatorMessage {
+    /// The whole view, sent when a spectator joins and when a new game starts
+    Snapshot { view: SpectatorView },
+    Change { change: GameChange }
+}
+
+impl SpectatorMessage {
+    fn apply_to(&self, view: &mut Option<SpectatorView>) {
+        match self {
+            SpectatorMessage::Snapshot { view: snapshot } => *view = Some(snapshot.clone()),
+            SpectatorMessage::Change { change } => {
+                if let Some(view) = view.as_mut() {
+                    change.apply_to(&mut view.cells);
+                    view.status = change.summary();
+                }
+            }
+        }
+    }
+}
+
+struct Broadcast {
+    delay: Duration,
+    /// Messages waiting for their delay to pass, oldest first
+    queue: VecDeque<(Instant, SpectatorMessage)>,
+    /// The view as far as it has been released to spectators
+    view: Option<SpectatorView>,
+    /// ID of the game the last published message belongs to
+    game_id: Option<u32>,
+    connections: Vec<(u32, TcpStream)>,
+    next_connection_id: u32,
+    /// Cleared when the broadcast stops, which stops its threads
+    running: Arc<AtomicBool>
+}
+
+impl Broadcast {
+    fn end(&self) {
+        self.running.store(false, Ordering::Relaxed);
+        for (_, stream) in &self.connections {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+struct Watch {
+    view: Option<SpectatorView>,
+    stream: TcpStream,
+    running: Arc<AtomicBool>
+}
+
+#[tauri::command]
+/// Starts publishing the local game to spectators, `delay_ms` milliseconds
+/// behind the actual game. Other windows can always watch through
+/// `get_spectator_view`; if `lan` is true, spectators on the LAN can also
+/// connect on `port` (or the default port). Returns the port, if any
+pub fn start_broadcast(delay_ms: Option<u64>, lan: bool, port: Option<u16>) -> Result<Option<u16>, String> {
+    let mut broadcast = BROADCAST.lock().map_err(|_| String::from("Couldn't lock the broadcast"))?;
+    if broadcast.is_some() {
+        return Err(String::from("Already broadcasting, stop that first"));
+    }
+
+    let running = Arc::new(AtomicBool::new(true));
+    let listener = if lan { Some(network::listen(port)?) } else { None };
+    *broadcast = Some(Broadcast {
+        delay: Duration::from_millis(delay_ms.unwrap_or(0)),
+        queue: VecDeque::new(),
+        view: None,
+        game_id: None,
+        connections: Vec::new(),
+        next_connection_id: 0,
+        running: running.clone()
+    });
+
+    let release_running = running.clone();
+    thread::spawn(move || release_messages(release_running));
+    Ok(listener.map(|(listener, port)| {
+        thread::spawn(move || network::accept_connections(listener, running, handle_spectator));
+        port
+    }))
+}
+
+#[tauri::command]
+/// Stops publishing the local game to spectators
+pub fn stop_broadcast() {
+    if let Ok(mut broadcast) = BROADCAST.lock() {
+        if let Some(broadcast) = broadcast.take() {
+            broadcast.end();
+        }
+    }
+}
+
+#[tauri::command]
+/// Starts watching the game broadcast at `address`, which is a host name or
+/// IP address, optionally followed by `:port`
+pub fn watch_game(address: String) -> Result<(), String> {
+    let mut watch = WATCH.lock().map_err(|_| String::from("Couldn't lock the watched game"))?;
+    if watch.is_some() {
+        return Err(String::from("Already watching a game, stop that first"));
+    }
+
+    let stream = network::connect(&address)?;
+    let reader = stream.try_clone().map_err(|e| e.to_string())?;
+    let running = Arc::new(AtomicBool::new(true));
+    *watch = Some(Watch { view: None, stream, running: running.clone() });
+
+    thread::spawn(move || read_broadcast(reader, running));
+    Ok(())
+}
+
+#[tauri::command]
+/// Stops watching the game of another instance
+pub fn stop_watching() {
+    if let Ok(mut watch) = WATCH.lock() {
+        if let Some(watch) = watch.take() {
+            watch.running.store(false, Ordering::Relaxed);
+            let _ = watch.stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+#[tauri::command]
+/// Returns what spectators currently see: the watched game of another
+/// instance, or otherwise the delayed broadcast of the local game
+pub fn get_spectator_view() -> Option<SpectatorView> {
+    if let Ok(watch) = WATCH.lock() {
+        if let Some(watch) = watch.as_ref() {
+            return watch.view.clone();
+        }
+    }
+    if let Ok(broadcast) = BROADCAST.lock() {
+        return broadcast.as_ref().and_then(|broadcast| broadcast.view.clone());
+    }
+    println!("ERROR (sp.gsv): Couldn't lock the broadcast");
+    None
+}
+
+/// Queues the result of a move on `board` for the spectators, if the game
+/// is being broadcast. The first change of a new game is sent as a snapshot
+pub fn publish(board: &Board, change: &GameChange) {
+    let Ok(mut broadcast) = BROADCAST.lock() else {
+        println!("ERROR (sp.p): Couldn't lock the broadcast");
+        return;
+    };
+    let Some(broadcast) = broadcast.as_mut() else { return; };
+
+    let message = if broadcast.game_id == Some(board.get_game_id()) {
+        SpectatorMessage::Change { change: change.clone() }
+    } else {
+        broadcast.game_id = Some(board.get_game_id());
+        SpectatorMessage::Snapshot {
+            view: SpectatorView {
+                status: change.summary(),
+                cells: board.clone_visible_cells()
+            }
+        }
+    };
+    let release_at = Instant::now() + broadcast.delay;
+    broadcast.queue.push_back((release_at, message));
+}
+
+/// Thread that sends the queued messages to the spectators once their delay has passed
+fn release_messages(running: Arc<AtomicBool>) {
+    while running.load(Ordering::Relaxed) {
+        thread::sleep(RELEASE_INTERVAL);
+        let Ok(mut broadcast) = BROADCAST.lock() else { continue; };
+        let Some(broadcast) = broadcast.as_mut() else { break; };
+        if !Arc::ptr_eq(&broadcast.running, &running) {
+            break;
+        }
+
+        let now = Instant::now();
+        while broadcast.queue.front().is_some_and(|(release_at, _)| *release_at <= now) {
+            let Some((_, message)) = broadcast.queue.pop_front() else { break; };
+            message.apply_to(&mut broadcast.view);
+            broadcast.connections.retain(|(_, stream)| network::send_message(stream, &message).is_ok());
+        }
+    }
+}
+
+/// Thread that sends the current view to a spectator that joined, and
+/// forgets the spectator once it disconnects
+fn handle_spectator(stream: TcpStream, running: Arc<AtomicBool>) {
+    let Ok(reader) = stream.try_clone() else { return; };
+    let connection_id = {
+        let Ok(mut broadcast) = BROADCAST.lock() else { return; };
+        let Some(broadcast) = broadcast.as_mut().filter(|b| Arc::ptr_eq(&b.running, &running)) else { return; };
+        if let Some(view) = &broadcast.view {
+            let _ = network::send_message(&stream, &SpectatorMessage::Snapshot { view: view.clone() });
+        }
+        let connection_id = broadcast.next_connection_id;
+        broadcast.next_connection_id += 1;
+        broadcast.connections.push((connection_id, stream));
+        connection_id
+    };
+
+    // Spectators don't send anything, this only waits for the connection to close
+    network::read_messages(reader, |_: Value| {});
+
+    if let Ok(mut broadcast) = BROADCAST.lock() {
+        if let Some(broadcast) = broadcast.as_mut().filter(|b| Arc::ptr_eq(&b.running, &running)) {
+            broadcast.connections.retain(|(id, _)| *id != connection_id);
+        }
+    }
+}
+
+/// Thread that keeps the view of a watched game up to date
+fn read_broadcast(reader: TcpStream, running: Arc<AtomicBool>) {
+    network::read_messages(reader, |message: SpectatorMessage| {
+        if let Ok(mut watch) = WATCH.lock() {
+            if let Some(watch) = watch.as_mut().filter(|w| Arc::ptr_eq(&w.running, &running)) {
+                message.apply_to(&mut watch.view);
+            }
+        }
+    });
+}
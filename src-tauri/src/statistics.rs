@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Serialize, Deserialize};
+
+use crate::storage;
+
+/// Statistics of games that don't match the active profile are grouped under this name
+pub const CUSTOM_PRESET: &str = "Custom";
+
+/// Name of the statistics file inside the app data directory
+const STATISTICS_FILE_NAME: &str = "statistics.json";
+
+/// Version of the statistics file written by this build
+const STATISTICS_VERSION: u32 = 1;
+
+static STATISTICS: Mutex<BTreeMap<String, PresetStatistics>> = Mutex::new(BTreeMap::new());
+static STATISTICS_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct PresetStatistics {
+    games_played: u32,
+    games_won: u32,
+    /// Current number of wins in a row
+    current_streak: u32,
+    best_streak: u32,
+    /// Fastest win in milliseconds
+    best_time: Option<u64>,
+    /// Sum of the durations of all won games in milliseconds
+    total_win_time: u64
+}
+
+/// The layout of the statistics file on disk
+#[derive(Serialize, Deserialize)]
+struct StatisticsFile {
+    version: u32,
+    presets: BTreeMap<String, PresetStatistics>
+}
+
+#[tauri::command]
+/// Returns the statistics of every preset that has been played
+pub fn get_statistics() -> BTreeMap<String, PresetStatistics> {
+    if let Ok(statistics) = STATISTICS.lock() {
+        statistics.clone()
+    } else {
+        println!("ERROR (st.gs): Couldn't lock the statistics");
+        BTreeMap::new()
+    }
+}
+
+#[tauri::command]
+/// Clears the statistics of one preset, or of all presets if `preset` is `None`
+pub fn reset_statistics(preset: Option<String>) {
+    if let Ok(mut statistics) = STATISTICS.lock() {
+        match preset {
+            Some(name) => { statistics.remove(&name); },
+            None => statistics.clear()
+        }
+        save_statistics(&statistics);
+    }
+}
+
+/// Records a finished game for `preset`
+pub fn record_game(preset: &str, won: bool, duration: Duration) {
+    if let Ok(mut statistics) = STATISTICS.lock() {
+        let entry = statistics.entry(String::from(preset)).or_default();
+        entry.games_played += 1;
+        if won {
+            let time = duration.as_millis() as u64;
+            entry.games_won += 1;
+            entry.current_streak += 1;
+            entry.best_streak = entry.best_streak.max(entry.current_streak);
+            entry.best_time = Some(entry.best_time.map_or(time, |best| best.min(time)));
+            entry.total_win_time += time;
+        } else {
+            entry.current_streak = 0;
+        }
+        save_statistics(&statistics);
+    } else {
+        println!("ERROR (st.rg): Couldn't lock the statistics, game wasn't recorded");
+    }
+}
+
+/// Moves the statistics of a renamed profile to its new name
+pub fn rename_preset(old_name: &str, new_name: &str) {
+    if let Ok(mut statistics) = STATISTICS.lock() {
+        if let Some(entry) = statistics.remove(old_name) {
+            statistics.insert(String::from(new_name), entry);
+            save_statistics(&statistics);
+        }
+    }
+}
+
+/// Loads the statistics file from `data_dir`
+pub fn load_statistics(data_dir: Option<PathBuf>) {
+    let Some(data_dir) = data_dir else {
+        println!("ERROR (st.ls): No app data directory available, statistics won't be saved");
+        return;
+    };
+    let path = data_dir.join(STATISTICS_FILE_NAME);
+
+    let loaded = storage::read_json_file(&path).and_then(|value| match value {
+        Some(value) => match serde_json::from_value::<StatisticsFile>(value) {
+            Ok(file) if file.version <= STATISTICS_VERSION => Ok(file.presets),
+            Ok(file) => Err(format!("statistics file version {} is newer than supported version {}", file.version, STATISTICS_VERSION)),
+            Err(e) => Err(e.to_string())
+        },
+        None => Ok(BTreeMap::new())
+    });
+    let presets = match loaded {
+        Ok(presets) => {
+            set_statistics_path(Some(path));
+            presets
+        }
+        Err(e) => {
+            println!("ERROR (st.ls): Couldn't load statistics file {}: {}", path.display(), e);
+            match storage::back_up_file(&path) {
+                Ok(backup) => {
+                    println!("ERROR (st.ls): Statistics file backed up to {}, starting with empty statistics", backup.display());
+                    set_statistics_path(Some(path));
+                }
+                // Rather lose the games of this session than the file
+                Err(e) => {
+                    println!("ERROR (st.ls): {}, statistics won't be saved", e);
+                    set_statistics_path(None);
+                }
+            }
+            BTreeMap::new()
+        }
+    };
+
+    if let Ok(mut statistics) = STATISTICS.lock() {
+        *statistics = presets;
+    }
+}
+
+fn set_statistics_path(path: Option<PathBuf>) {
+    if let Ok(mut statistics_path) = STATISTICS_PATH.lock() {
+        *statistics_path = path;
+    } else {
+        println!("ERROR (st.ssp): Couldn't lock the statistics path");
+    }
+}
+
+/// Writes `statistics` to the statistics file, if its location is known
+fn save_statistics(statistics: &BTreeMap<String, PresetStatistics>) {
+    let path = match STATISTICS_PATH.lock() {
+        Ok(path) => path.clone(),
+        Err(_) => {
+            println!("ERROR (st.ss): Couldn't lock the statistics path");
+            return;
+        }
+    };
+    let Some(path) = path else { return; };
+
+    let file = StatisticsFile {
+        version: STATISTICS_VERSION,
+        presets: statistics.clone()
+    };
+    if let Err(e) = storage::write_json_file(&path, &file) {
+        println!("ERROR (st.ss): {}", e);
+    }
+}
@@ -0,0 +1,36 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// Reads the JSON file at `path`. Returns `Ok(None)` if the file doesn't
+/// exist yet, and an error if it exists but can't be read or parsed
+pub fn read_json_file(path: &Path) -> Result<Option<Value>, String> {
+    match fs::read_to_string(path) {
+        Ok(contents) => serde_json::from_str::<Value>(&contents)
+            .map(Some)
+            .map_err(|e| format!("{} is not valid JSON: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Couldn't read {}: {}", path.display(), e))
+    }
+}
+
+/// Writes `value` as JSON to `path`, creating the parent directory if needed
+pub fn write_json_file<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Couldn't create directory {}: {}", dir.display(), e))?;
+    }
+    let contents = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    fs::write(path, contents).map_err(|e| format!("Couldn't write {}: {}", path.display(), e))
+}
+
+/// Copies the file at `path` next to it with `.bak` added to its name, so a
+/// file that couldn't be read isn't lost when it is written again
+pub fn back_up_file(path: &Path) -> Result<PathBuf, String> {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".bak");
+    let backup = PathBuf::from(backup);
+    fs::copy(path, &backup).map_err(|e| format!("Couldn't back up {} to {}: {}", path.display(), backup.display(), e))?;
+    Ok(backup)
+}
@@ -0,0 +1,270 @@
+use std::sync::Mutex;
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+use serde::{Serialize, Deserialize};
+
+use crate::board::{Board, CellDetails};
+use crate::boardgenerator;
+use crate::solver::{Deduction, Solver, Technique};
+
+/// The drill that is being played, if any
+static DRILL: Mutex<Option<Drill>> = Mutex::new(None);
+
+/// Number of positions generated at most for a drill before giving up
+const MAX_DRILL_ATTEMPTS: u32 = 200;
+
+/// The patterns that can be practiced
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum Pattern {
+    /// A 2 between two 1s along a wall: the mines are next to the 2
+    OneTwoOne,
+    /// Two 2s between two 1s along a wall: the mines are next to the 2s
+    OneTwoTwoOne,
+    /// Two 1s next to each other at the edge of the board: the cell past
+    /// the second 1 is safe
+    CornerOneOne,
+    /// Numbers that touch flagged mines count as smaller numbers, which can
+    /// then be compared with their neighbours
+    Reduction,
+    /// Only the number of mines that are left decides the last cells
+    MineCount
+}
+
+/// Positions for each pattern, in the format of `boardgenerator::create_position`.
+/// The hidden cells marked `*` and `-` are the ones the pattern decides
+const ONE_TWO_ONE: &[&[&str]] = &[
+    &["m-*-*-m",
+      ".......",
+      ".......",
+      "......."],
+    &["???????",
+      "m-*-*-m",
+      ".......",
+      "......."]
+];
+const ONE_TWO_TWO_ONE: &[&[&str]] = &[
+    &["?--**--?",
+      "........",
+      "........",
+      "........"],
+    &["????????",
+      "m--**--m",
+      "........",
+      "........"]
+];
+const CORNER_ONE_ONE: &[&[&str]] = &[
+    &["??-??",
+      ".....",
+      ".....",
+      "....."],
+    &["??-???",
+      "......",
+      "?.....",
+      "?....."]
+];
+const REDUCTION: &[&[&str]] = &[
+    &["?F???F",
+      "......",
+      "......",
+      "......"],
+    &["??F???",
+      "?.....",
+      "F.....",
+      "?....."],
+    &["Fms-ms",
+      "F.....",
+      "......",
+      "......"]
+];
+const MINE_COUNT: &[&[&str]] = &[
+    &["-FFms",
+      "FFF..",
+      ".....",
+      "....."],
+    &["*FFms",
+      "FFF..",
+      ".....",
+      "....."]
+];
+
+impl Pattern {
+    const ALL: [Pattern; 5] = [Pattern::OneTwoOne, Pattern::OneTwoTwoOne, Pattern::CornerOneOne,
+        Pattern::Reduction, Pattern::MineCount];
+
+    fn templates(&self) -> &'static [&'static [&'static str]] {
+        match self {
+            Pattern::OneTwoOne => ONE_TWO_ONE,
+            Pattern::OneTwoTwoOne => ONE_TWO_TWO_ONE,
+            Pattern::CornerOneOne => CORNER_ONE_ONE,
+            Pattern::Reduction => REDUCTION,
+            Pattern::MineCount => MINE_COUNT
+        }
+    }
+
+    /// The technique the solver needs for the pattern. A position is only
+    /// used if the techniques below this one can't solve it
+    fn technique(&self) -> Technique {
+        match self {
+            Pattern::MineCount => Technique::Enumeration,
+            _ => Technique::Pair
+        }
+    }
+}
+
+struct Drill {
+    board: Board,
+    /// Everything that can be proven in the position
+    forced: Deduction
+}
+
+/// A drill position for the front-end
+#[derive(Serialize)]
+pub struct DrillPosition {
+    drill_id: u32,
+    pattern: Pattern,
+    /// Total number of mines, including the flagged ones
+    mines: usize,
+    cells: Vec<Vec<CellDetails>>
+}
+
+/// How an answer to a drill was graded
+#[derive(Serialize)]
+pub struct DrillResult {
+    /// True if every forced cell was marked, and nothing else
+    passed: bool,
+    /// Cells that were marked as what they are forced to be
+    correct: Vec<(usize, usize)>,
+    /// Cells that were marked, but aren't forced to be what they were marked as
+    wrong: Vec<(usize, usize)>,
+    /// Forced cells that weren't marked
+    missed: Vec<(usize, usize)>,
+    forced_mines: Vec<(usize, usize)>,
+    forced_safe: Vec<(usize, usize)>
+}
+
+#[tauri::command]
+/// Starts a drill for `pattern`, or a random pattern if none is given.
+/// The player has to find every hidden cell that is forced to be a mine or safe
+pub fn start_drill(pattern: Option<Pattern>) -> Result<DrillPosition, String> {
+    let mut rng = thread_rng();
+    let pattern = pattern.unwrap_or_else(|| Pattern::ALL[rng.gen_range(0..Pattern::ALL.len())]);
+    let (board, forced) = generate_drill(pattern, &mut rng)?;
+
+    let position = DrillPosition {
+        drill_id: board.get_game_id(),
+        pattern,
+        mines: board.get_mine_total(),
+        cells: board.clone_visible_cells()
+    };
+    let mut drill = DRILL.lock().map_err(|_| String::from("Couldn't lock the drill"))?;
+    *drill = Some(Drill { board, forced });
+    Ok(position)
+}
+
+#[tauri::command]
+/// Grades the answer to drill `drill_id`: the cells the player marked as
+/// `mines` and as `safe`
+pub fn submit_drill(drill_id: u32, mines: Vec<(usize, usize)>, safe: Vec<(usize, usize)>) -> Result<DrillResult, String> {
+    let drill = DRILL.lock().map_err(|_| String::from("Couldn't lock the drill"))?;
+    let Some(drill) = drill.as_ref().filter(|drill| drill.board.get_game_id() == drill_id) else {
+        return Err(format!("Drill {} isn't being played", drill_id));
+    };
+
+    let mut correct = Vec::new();
+    let mut wrong = Vec::new();
+    for (marked, forced) in [(&mines, &drill.forced.mines), (&safe, &drill.forced.safe)] {
+        for cell in marked {
+            if forced.contains(cell) {
+                correct.push(*cell);
+            } else {
+                wrong.push(*cell);
+            }
+        }
+    }
+    let missed: Vec<(usize, usize)> = drill.forced.mines.iter()
+        .chain(&drill.forced.safe)
+        .filter(|cell| !correct.contains(cell))
+        .copied()
+        .collect();
+
+    Ok(DrillResult {
+        passed: wrong.is_empty() && missed.is_empty(),
+        correct,
+        wrong,
+        missed,
+        forced_mines: drill.forced.mines.clone(),
+        forced_safe: drill.forced.safe.clone()
+    })
+}
+
+/// Generates positions for `pattern` until one of them needs the pattern.
+/// Returns the board and every cell that is forced in it
+fn generate_drill(pattern: Pattern, rng: &mut impl Rng) -> Result<(Board, Deduction), String> {
+    let easier = match pattern.technique() {
+        Technique::Single => None,
+        Technique::Pair => Some(Technique::Single),
+        Technique::Enumeration => Some(Technique::Pair)
+    };
+
+    for _ in 0..MAX_DRILL_ATTEMPTS {
+        let Some(template) = pattern.templates().choose(rng) else { break; };
+        let template = transform(template, rng);
+        // The random cells of a template can leave it without any mines
+        let Ok(board) = boardgenerator::create_position(&template, rng) else { continue; };
+
+        let solver = Solver::from_visible(&board);
+        let pattern_forced = solver.forced_cells(pattern.technique());
+        let mut targets: Vec<((usize, usize), bool)> = Vec::new();
+        for (y, row) in template.iter().enumerate() {
+            for (x, c) in row.iter().enumerate() {
+                match c {
+                    '*' => targets.push(((x, y), true)),
+                    '-' => targets.push(((x, y), false)),
+                    _ => {}
+                }
+            }
+        }
+        // Positions without marked cells only need something to be forced
+        if targets.is_empty() {
+            targets.extend(pattern_forced.mines.iter().map(|cell| (*cell, true)));
+            targets.extend(pattern_forced.safe.iter().map(|cell| (*cell, false)));
+        }
+
+        let is_forced = |deduction: &Deduction, (cell, mine): &((usize, usize), bool)| {
+            if *mine { deduction.mines.contains(cell) } else { deduction.safe.contains(cell) }
+        };
+        if targets.is_empty() || !targets.iter().all(|target| is_forced(&pattern_forced, target)) {
+            continue;
+        }
+        if let Some(easier) = easier {
+            let easier_forced = solver.forced_cells(easier);
+            if targets.iter().all(|target| is_forced(&easier_forced, target)) {
+                continue;
+            }
+        }
+        // Answers are graded against everything the solver can prove
+        let forced = solver.forced_cells(Technique::Enumeration);
+        return Ok((board, forced));
+    }
+    Err(format!("Couldn't generate a position for {:?}", pattern))
+}
+
+/// Turns `template` into rows of cells, randomly rotated and mirrored
+fn transform(template: &[&str], rng: &mut impl Rng) -> Vec<Vec<char>> {
+    let mut rows: Vec<Vec<char>> = template.iter().map(|row| row.chars().collect()).collect();
+    if rng.gen_bool(0.5) {
+        let width = rows.first().map_or(0, |row| row.len());
+        rows = (0..width).map(|x| rows.iter().map(|row| row[x]).collect()).collect();
+    }
+    if rng.gen_bool(0.5) {
+        rows.reverse();
+    }
+    if rng.gen_bool(0.5) {
+        for row in rows.iter_mut() {
+            row.reverse();
+        }
+    }
+    rows
+}
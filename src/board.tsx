@@ -6,13 +6,21 @@ import { BoardLogicProvider } from "./boardlogic.tsx";
 export default function Board(props: any) {
     const coords_x = [...Array(props.boardWidth())];
     const coords_y = [...Array(props.boardHeight())];
+    const coords_z = [...Array(props.boardDepth())];
 
+    // The layers of a 3D board are shown below each other, and the backend
+    // counts the rows of all layers together
     return <div id="gameboard">
         <BoardLogicProvider {...props}>
-            <For each={coords_y}>{ (_, y) => (
-                <div class="boardrow">
-                    <For each={coords_x}>{(_, x) => (
-                        <Cell x={x()} y={y()} />
+            <For each={coords_z}>{ (_, z) => (
+                <div class="boardlayer">
+                    <For each={coords_y}>{ (_, y) => (
+                        <div class="boardrow">
+                            <For each={coords_x}>{(_, x) => (
+                                <Cell x={x()} y={z() * props.boardHeight() + y()} />
+                                )}
+                            </For>
+                        </div>
                         )}
                     </For>
                 </div>
@@ -1,35 +1,120 @@
 import { createStore, produce } from "solid-js/store";
-import { createContext, batch, createSignal, useContext } from "solid-js";
+import { createContext, batch, createSignal, onCleanup, useContext } from "solid-js";
 import { invoke } from "@tauri-apps/api";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
 import { getContext, GameState } from "./customines.tsx";
 
 const BoardLogicContext = createContext();
 
+// Either the number of a cell without mines (negative next to anti-mines),
+// how many mines a cell holds, or an anti-mine
+type CellContent = {
+    Number?: number,
+    Mines?: number
+} | "AntiMine";
+
+// Shown instead of the number of a cell on a fog-of-war board
+type Clue = { Range: [number, number] } | "Even" | "Odd";
+
 interface CellDetails {
     state: string,
-    value: number
+    content: CellContent,
+    flags: number,
+    clue: Clue | null
+}
+
+interface Difficulty {
+    bbbv: number,
+    openings: number,
+    forced_guesses: number,
+    hardest_technique: string | null,
+    rating: number
+}
+
+// The cells of a new board aren't sent, as they are all hidden
+interface GeneratedBoard {
+    width: number,
+    height: number,
+    game_id: number,
+    difficulty: Difficulty | null
+}
+
+// Something that happened to the board besides cells changing
+interface GameEvent {
+    type: string,
+    moved?: number
 }
 
 interface GameChange {
+    game_id: number,
     hidden_cell_count: number,
     flag_count: number,
     mines_remaining: number,
+    anti_mines_remaining: number,
     game_state: string,
-    changes: BoardChange[]
+    pokes_until_move: number | null,
+    changes: CompactChanges,
+    events: GameEvent[]
 }
 
+// Uncovered cells with a plain number come as runs along a row, which are
+// applied before the other changes
+interface CompactChanges {
+    runs: NumberRun[],
+    other: BoardChange[]
+}
+
+interface NumberRun {
+    x: number,
+    y: number,
+    numbers: number[]
+}
+
+// Fields that don't change are left out
 interface BoardChange {
     x: number,
     y: number,
-    state: string | null,
-    value: number | null
+    state?: string,
+    content?: CellContent,
+    clue?: Clue,
+    flags?: number
+}
+
+interface Hint {
+    x: number,
+    y: number,
+    kind: string,
+    technique: string | null,
+    mine_chance: number
+}
+
+// Sent by the backend when the next move on the board can be proven
+interface HintAvailable {
+    game_id: number,
+    hint: Hint
 }
 
 export function BoardLogicProvider(props: any) {
     const [cellDetails, setCellDetails] = createStore<CellDetails[][]>([]);
     const [firstMove, setFirstMove] = createSignal(true);
+    const [pokesUntilMove, setPokesUntilMove] = createSignal<number | null>(null);
+    const [minesMoved, setMinesMoved] = createSignal(0);
+    const [gameId, setGameId] = createSignal<number | null>(null);
+    const [availableHint, setAvailableHint] = createSignal<Hint | null>(null);
     const app = getContext();
 
+    // The backend sends every change to the board as an event, whoever made
+    // it, so poking and marking don't use what the commands return
+    const listeners: Promise<UnlistenFn>[] = [
+        listen<GameChange>("board-changed", event => {
+            if (event.payload.game_id === gameId()) { processChanges(event.payload); }
+        }),
+        listen<HintAvailable>("hint-available", event => {
+            if (event.payload.game_id === gameId()) { setAvailableHint(event.payload.hint); }
+        })
+    ];
+    onCleanup(() => listeners.forEach(async unlisten => (await unlisten)()));
+
     // Left click action of a cell
     async function poke(x: number, y: number) {
         // Disable poking on a game-over/game-won
@@ -41,37 +126,92 @@ export function BoardLogicProvider(props: any) {
             // Formally start the game
             app.setGameState(GameState.Playing);
         }
-        processChanges(await invoke("poke", {
+        await invoke("poke", {
             x: x,
-            y: y
-        }));
+            y: y,
+            gameId: gameId()
+        });
     }
 
     // Right click action of a cell
     async function mark(x: number, y: number) {
         // Disable marking when the game hasn't started yet
         if (app.gameState() != GameState.Playing) { return; }
-        processChanges(await invoke('mark', {
+        await invoke('mark', {
             x: x,
-            y: y
-        }));
+            y: y,
+            gameId: gameId()
+        });
+    }
+
+    // Suggests the next move, or null if there is none
+    async function getHint(): Promise<Hint | null> {
+        if (app.gameState() != GameState.Playing) { return null; }
+        try {
+            return await invoke("get_hint");
+        } catch (e) {
+            console.log(e);
+            return null;
+        }
+    }
+
+    // Lets the bot make the next move. Its changes arrive as board-changed
+    async function botStep() {
+        if (app.gameState() != GameState.Playing) { return; }
+        try {
+            await invoke("bot_step", { gameId: gameId() });
+        } catch (e) {
+            console.log(e);
+        }
+    }
+
+    // Lets the bot play the rest of the game, with delayMs between moves
+    async function startAutoPlay(delayMs?: number) {
+        if (app.gameState() != GameState.Playing) { return; }
+        try {
+            await invoke("start_auto_play", { gameId: gameId(), delayMs: delayMs });
+        } catch (e) {
+            console.log(e);
+        }
+    }
+
+    async function stopAutoPlay() {
+        await invoke("stop_auto_play");
     }
 
     async function generateBoard(firstX: number, firstY: number) {
-        setCellDetails(await invoke("generate_board", {
-            width: props.boardWidth(),
-            height: props.boardHeight(),
-            mines: props.mineCount(),
-            firstX: firstX, 
-            firstY: firstY
-        }))
+        const generated: GeneratedBoard = await invoke("generate_board", {
+            request: {
+                width: props.boardWidth(),
+                height: props.boardHeight(),
+                depth: props.boardDepth(),
+                mines: props.mineCount(),
+                first_x: firstX,
+                first_y: firstY
+            }
+        });
+        setCellDetails([...Array(generated.height)].map(() => [...Array(generated.width)].map(() => ({
+            state: "Hidden",
+            content: { Number: 0 },
+            flags: 0,
+            clue: null
+        }))));
+        setGameId(generated.game_id);
     }
 
     function processChanges(gameChanges: GameChange) {
         batch(() => {
+            // A hint for the board before this change may no longer apply
+            setAvailableHint(null);
             props.setFlagCount(gameChanges.flag_count);
             props.setHiddenCellCount(gameChanges.hidden_cell_count);
             props.setMinesRemaining(gameChanges.mines_remaining);
+            setPokesUntilMove(gameChanges.pokes_until_move);
+            gameChanges.events.forEach(event => {
+                if (event.type === "MinesMoved") {
+                    setMinesMoved(minesMoved() + event.moved!);
+                }
+            });
             switch (gameChanges.game_state) {
                 case "Playing":
                     app.setGameState(GameState.Playing);
@@ -85,12 +225,24 @@ export function BoardLogicProvider(props: any) {
             }
 
             // Cell changes
-            gameChanges.changes.forEach(change => {
+            gameChanges.changes.runs.forEach(run => {
+                setCellDetails(produce((cells) => run.numbers.forEach((number, offset) => {
+                    cells[run.y][run.x + offset].state = "ShowValue";
+                    cells[run.y][run.x + offset].content = { Number: number };
+                })));
+            });
+            gameChanges.changes.other.forEach(change => {
                 if (change.state != null) {
                     setCellDetails(produce((cells) => cells[change.y][change.x].state = change.state!)); 
                 }
-                if (change.value != null) {
-                    setCellDetails(produce((cells) => cells[change.y][change.x].value = change.value!));
+                if (change.content != null) {
+                    setCellDetails(produce((cells) => cells[change.y][change.x].content = change.content!));
+                }
+                if (change.clue != null) {
+                    setCellDetails(produce((cells) => cells[change.y][change.x].clue = change.clue!));
+                }
+                if (change.flags != null) {
+                    setCellDetails(produce((cells) => cells[change.y][change.x].flags = change.flags!));
                 }
             });
         })
@@ -108,7 +260,50 @@ export function BoardLogicProvider(props: any) {
     function getValue(x: number, y: number): number {
         if (cellDetails.length > y && y >= 0) {
             if (cellDetails[y].length > x && x >= 0) {
-                return cellDetails[y][x].value;
+                const content = cellDetails[y][x].content;
+                return typeof content === "object" ? content.Number ?? 0 : 0;
+            }
+        }
+        return 0;
+    }
+
+    function isMine(x: number, y: number): boolean {
+        if (cellDetails.length > y && y >= 0) {
+            if (cellDetails[y].length > x && x >= 0) {
+                const content = cellDetails[y][x].content;
+                return typeof content === "object" && content.Mines != null;
+            }
+        }
+        return false;
+    }
+
+    function isAntiMine(x: number, y: number): boolean {
+        if (cellDetails.length > y && y >= 0) {
+            if (cellDetails[y].length > x && x >= 0) {
+                return cellDetails[y][x].content === "AntiMine";
+            }
+        }
+        return false;
+    }
+
+    // The text of a fuzzy number, or null if the number is exact
+    function getClueText(x: number, y: number): string | null {
+        if (cellDetails.length > y && y >= 0) {
+            if (cellDetails[y].length > x && x >= 0) {
+                const clue = cellDetails[y][x].clue;
+                if (clue == null) { return null; }
+                if (clue === "Even") { return "even"; }
+                if (clue === "Odd") { return "odd"; }
+                return clue.Range[0] + "-" + clue.Range[1];
+            }
+        }
+        return null;
+    }
+
+    function getFlags(x: number, y: number): number {
+        if (cellDetails.length > y && y >= 0) {
+            if (cellDetails[y].length > x && x >= 0) {
+                return cellDetails[y][x].flags;
             }
         }
         return 0;
@@ -117,8 +312,19 @@ export function BoardLogicProvider(props: any) {
     const functions = {
         poke: poke,
         mark: mark,
+        getHint: getHint,
+        botStep: botStep,
+        startAutoPlay: startAutoPlay,
+        stopAutoPlay: stopAutoPlay,
         getState: getState,
         getValue: getValue,
+        isMine: isMine,
+        isAntiMine: isAntiMine,
+        getClueText: getClueText,
+        pokesUntilMove: pokesUntilMove,
+        minesMoved: minesMoved,
+        availableHint: availableHint,
+        getFlags: getFlags,
     }
 
     return <BoardLogicContext.Provider value={functions}>
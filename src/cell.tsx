@@ -10,9 +10,12 @@ export default function Cell(props: any) {
         cell: true, 
         hidden: logic.getState(props.x, props.y) === "Hidden",
         flagged: logic.getState(props.x, props.y) === "Flagged",
+        antiFlagged: logic.getState(props.x, props.y) === "AntiFlagged",
         questioned: logic.getState(props.x, props.y) === "Questioned",
         showValue: logic.getState(props.x, props.y) === "ShowValue",
-        showBlank: logic.getState(props.x, props.y) === "ShowValue" && logic.getValue(props.x, props.y) === 0,
+        showBlank: logic.getState(props.x, props.y) === "ShowValue" && logic.getValue(props.x, props.y) === 0 &&
+            !logic.isMine(props.x, props.y) && !logic.isAntiMine(props.x, props.y) &&
+            logic.getClueText(props.x, props.y) == null,
         showValue1: logic.getState(props.x, props.y) === "ShowValue" && logic.getValue(props.x, props.y) === 1,
         showValue2: logic.getState(props.x, props.y) === "ShowValue" && logic.getValue(props.x, props.y) === 2,
         showValue3: logic.getState(props.x, props.y) === "ShowValue" && logic.getValue(props.x, props.y) === 3,
@@ -21,7 +24,8 @@ export default function Cell(props: any) {
         showValue6: logic.getState(props.x, props.y) === "ShowValue" && logic.getValue(props.x, props.y) === 6,
         showValue7: logic.getState(props.x, props.y) === "ShowValue" && logic.getValue(props.x, props.y) === 7,
         showValue8: logic.getState(props.x, props.y) === "ShowValue" && logic.getValue(props.x, props.y) === 8,
-        showMine: logic.getState(props.x, props.y) === "ShowValue" && logic.getValue(props.x, props.y) === 9,
+        showMine: logic.getState(props.x, props.y) === "ShowValue" && logic.isMine(props.x, props.y),
+        showAntiMine: logic.getState(props.x, props.y) === "ShowValue" && logic.isAntiMine(props.x, props.y),
         showInvalidMine: logic.getState(props.x, props.y) === "ShowInvalidMine",
         showMineExploded: logic.getState(props.x, props.y) === "ShowMineExploded"
     }} 
@@ -31,11 +35,23 @@ export default function Cell(props: any) {
         <Show when={
             logic.getState(props.x, props.y) === "ShowValue" &&
             logic.getValue(props.x, props.y) !== 0 &&
-            logic.getValue(props.x, props.y) !== 9
+            !logic.isMine(props.x, props.y) &&
+            !logic.isAntiMine(props.x, props.y) &&
+            logic.getClueText(props.x, props.y) == null
         }>
             <p class="cellValue">
                 {logic.getValue(props.x, props.y)}
             </p>
         </Show>
+        <Show when={logic.getState(props.x, props.y) === "ShowValue" && logic.getClueText(props.x, props.y) != null}>
+            <p class="cellValue fuzzyClue">
+                {logic.getClueText(props.x, props.y)}
+            </p>
+        </Show>
+        <Show when={logic.getState(props.x, props.y) === "Flagged" && logic.getFlags(props.x, props.y) > 1}>
+            <p class="cellValue">
+                {logic.getFlags(props.x, props.y)}
+            </p>
+        </Show>
     </div>
 }
\ No newline at end of file
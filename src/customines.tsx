@@ -1,10 +1,11 @@
-import { createSignal, createContext, useContext, Switch, Match } from "solid-js";
+import { createSignal, createContext, useContext, onMount, Switch, Match } from "solid-js";
 import MainMenu from "./mainmenu.tsx"
 import Game from "./game.tsx"
 import { invoke } from "@tauri-apps/api";
 
 export const MAX_BOARD_HEIGHT = 100;
 export const MAX_BOARD_WIDTH = 100;
+export const MAX_BOARD_DEPTH = 10;
 
 const CustominesContext = createContext();
 
@@ -25,11 +26,21 @@ export function Customines() {
     const [boardHeight, setBoardHeight] = createSignal(7);
     const [boardWidth, setBoardWidth] = createSignal(9);
     const [mineCount, setMineCount] = createSignal(10);
+    // Number of layers, 1 for a flat board
+    const [boardDepth, setBoardDepth] = createSignal(1);
     const [questionsEnabled, setQuestionsEnabled] = createSignal(true);
     const [quickUncover, setQuickUncover] = createSignal(true);
     const [protectedQuickUncover, setProtectedQuickUncover] = createSignal(true);
     const settings = getContext();
 
+    // The backend loads the saved settings from disk, so use those
+    onMount(async () => {
+        const saved: any = await invoke("get_game_settings");
+        setQuestionsEnabled(saved.questions_enabled);
+        setQuickUncover(saved.quick_uncover);
+        setProtectedQuickUncover(saved.protected_quick_uncover);
+    });
+
     async function setGameSettings() {
         await invoke("set_game_settings", {
             settings: {
@@ -40,9 +51,9 @@ export function Customines() {
         });
     }
 
-    const gameProps = {boardHeight, boardWidth, mineCount, setGameSettings};
-    const mainMenuProps = {boardHeight, boardWidth, mineCount, 
-        setBoardHeight, setBoardWidth, setMineCount,
+    const gameProps = {boardHeight, boardWidth, boardDepth, mineCount, setGameSettings};
+    const mainMenuProps = {boardHeight, boardWidth, boardDepth, mineCount, 
+        setBoardHeight, setBoardWidth, setBoardDepth, setMineCount,
         questionsEnabled, setQuestionsEnabled,
         quickUncover, setQuickUncover,
         protectedQuickUncover, setProtectedQuickUncover
@@ -1,12 +1,14 @@
 import { createSignal, mergeProps, onCleanup, onMount, splitProps, Switch, Match } from "solid-js";
+import { listen } from "@tauri-apps/api/event";
 import Board from "./board.tsx";
 import { GameState, getContext, Screen } from "./customines.tsx";
 
 export default function Game(props: any) {
-    const [someboardprops, other] = splitProps(props, ["boardHeight", "boardWidth", "mineCount"]);
-    const [hiddenCellCount, setHiddenCellCount] = createSignal(props.boardWidth() * props.boardHeight());
+    const [someboardprops, other] = splitProps(props, ["boardHeight", "boardWidth", "boardDepth", "mineCount"]);
+    const [hiddenCellCount, setHiddenCellCount] = createSignal(props.boardWidth() * props.boardHeight() * props.boardDepth());
     const [flagCount, setFlagCount] = createSignal(0);
     const [minesRemaining, setMinesRemaining] = createSignal(props.mineCount());
+    const [elapsedSeconds, setElapsedSeconds] = createSignal(0);
     const boardprops = mergeProps(someboardprops, {hiddenCellCount, setHiddenCellCount, 
         flagCount, setFlagCount, minesRemaining, setMinesRemaining});
     const app = getContext();
@@ -22,6 +24,12 @@ export default function Game(props: any) {
         window.removeEventListener('contextmenu', rightClickEventOverride);
     });
 
+    // The backend sends the time every second while the game is played
+    const stopTimer = listen<{ game_id: number, elapsed_ms: number }>("timer-tick", event => {
+        setElapsedSeconds(Math.floor(event.payload.elapsed_ms / 1000));
+    });
+    onCleanup(async () => (await stopTimer)());
+
     app.setGameState(GameState.BeforeGame);
     props.setGameSettings();
 
@@ -34,6 +42,8 @@ export default function Game(props: any) {
         <br />
         Flags: {flagCount()}
         <br />
+        Time: {elapsedSeconds()}
+        <br />
         <Switch>
             <Match when={app.gameState() === GameState.GameOver }>
                 Git gud
@@ -1,16 +1,54 @@
-import { getContext, MAX_BOARD_HEIGHT, MAX_BOARD_WIDTH, Screen } from "./customines.tsx"
-import { Show } from "solid-js";
+import { getContext, MAX_BOARD_DEPTH, MAX_BOARD_HEIGHT, MAX_BOARD_WIDTH, Screen } from "./customines.tsx"
+import { createEffect, createSignal, on, Show } from "solid-js";
+import { invoke } from "@tauri-apps/api";
+
+// The most mines a board can have, keeping the cells around the first
+// click free: 9 cells on a flat board, and 27 with the layers around it
+function maxMines(width: number, height: number, depth: number) {
+    return width * height * depth - (depth > 1 ? 27 : 9);
+}
+
+// Number of games the bot plays to estimate how hard a board is
+const ESTIMATE_GAMES = 200;
+
+type SimulationReport = {
+    games: number,
+    wins: number,
+    win_probability: number,
+    average_guesses: number,
+    average_bbbv: number
+}
 
 export default function MainMenu(props: any) {
     const settings = getContext();
+    const [estimate, setEstimate] = createSignal<SimulationReport | null>(null);
+    // An estimate is only for the board it was made for
+    createEffect(on([props.boardWidth, props.boardHeight, props.boardDepth, props.mineCount],
+        () => setEstimate(null), { defer: true }));
+
+    // Lets the bot play boards of the chosen size to tell how likely they are to be won
+    async function estimateDifficulty() {
+        try {
+            setEstimate(await invoke("simulate_games", {
+                width: props.boardWidth(),
+                height: props.boardHeight(),
+                depth: props.boardDepth(),
+                mines: props.mineCount(),
+                games: ESTIMATE_GAMES
+            }));
+        } catch (e) {
+            console.log(e);
+            setEstimate(null);
+        }
+    }
 
     function increaseBoardWidth() { 
         let old_value = props.boardWidth();
         if (old_value < MAX_BOARD_WIDTH) {
             props.setBoardWidth((c: number) => c + 1);
             // If the previous mine-count was the max, we increase it to the new max
-            let old_max = old_value * props.boardHeight() - 9;
-            if (props.mineCount() == old_max) props.setMineCount((old_value + 1) * props.boardHeight() - 9);
+            let old_max = maxMines(old_value, props.boardHeight(), props.boardDepth());
+            if (props.mineCount() == old_max) props.setMineCount(maxMines(old_value + 1, props.boardHeight(), props.boardDepth()));
         }
     }
     function increaseBoardHeight() {
@@ -18,8 +56,8 @@ export default function MainMenu(props: any) {
         if (old_value < MAX_BOARD_HEIGHT) {
             props.setBoardHeight((c: number) => c + 1);
             // If the previous mine-count was the max, we increase it to the new max
-            let old_max = old_value * props.boardWidth() - 9;
-            if (props.mineCount() == old_max) props.setMineCount((old_value + 1) * props.boardWidth() - 9);
+            let old_max = maxMines(props.boardWidth(), old_value, props.boardDepth());
+            if (props.mineCount() == old_max) props.setMineCount(maxMines(props.boardWidth(), old_value + 1, props.boardDepth()));
         }
     }
 
@@ -28,7 +66,7 @@ export default function MainMenu(props: any) {
         if (old_value > 4) {
             props.setBoardWidth((c: number) => c - 1);
             // If the previous mine-count is greater than our new max, set it to the new max
-            let new_max = (old_value - 1) * props.boardHeight() - 9;
+            let new_max = maxMines(old_value - 1, props.boardHeight(), props.boardDepth());
             if (props.mineCount() > new_max) {
                 props.setMineCount(new_max);
             }
@@ -39,7 +77,27 @@ export default function MainMenu(props: any) {
         if (old_value > 4) {
             props.setBoardHeight((c: number) => c - 1);
             // If the previous mine-count is greater than our new max, set it to the new max
-            let new_max = (old_value - 1) * props.boardWidth() - 9;
+            let new_max = maxMines(props.boardWidth(), old_value - 1, props.boardDepth());
+            if (props.mineCount() > new_max) {
+                props.setMineCount(new_max);
+            }
+        }
+    }
+    function increaseBoardDepth() {
+        let old_value = props.boardDepth();
+        if (old_value < MAX_BOARD_DEPTH) {
+            props.setBoardDepth((c: number) => c + 1);
+            // If the previous mine-count was the max, we increase it to the new max
+            let old_max = maxMines(props.boardWidth(), props.boardHeight(), old_value);
+            if (props.mineCount() == old_max) props.setMineCount(maxMines(props.boardWidth(), props.boardHeight(), old_value + 1));
+        }
+    }
+    function decreaseBoardDepth() {
+        let old_value = props.boardDepth();
+        if (old_value > 1) {
+            props.setBoardDepth((c: number) => c - 1);
+            // If the previous mine-count is greater than our new max, set it to the new max
+            let new_max = maxMines(props.boardWidth(), props.boardHeight(), old_value - 1);
             if (props.mineCount() > new_max) {
                 props.setMineCount(new_max);
             }
@@ -47,7 +105,7 @@ export default function MainMenu(props: any) {
     }
     function decreaseMineCount() { if (props.mineCount() > 1) props.setMineCount((c: number) => c - 1) }
     function increaseMineCount() { 
-        if (props.mineCount() < (props.boardHeight() * props.boardWidth() - 9)) 
+        if (props.mineCount() < maxMines(props.boardWidth(), props.boardHeight(), props.boardDepth())) 
             props.setMineCount((c: number) => c + 1) 
     }
 
@@ -68,6 +126,13 @@ export default function MainMenu(props: any) {
             <button onClick={increaseBoardHeight}>+</button>
         </div>
         <br />
+        <div style="display: inline;">
+            Layers:
+            <button onClick={decreaseBoardDepth}>-</button>
+            {props.boardDepth()}
+            <button onClick={increaseBoardDepth}>+</button>
+        </div>
+        <br />
         <div style="display: inline;">
             # mines:
             <button onClick={decreaseMineCount}>-</button>
@@ -75,6 +140,17 @@ export default function MainMenu(props: any) {
             <button onClick={increaseMineCount}>+</button>
         </div>
         <br />
+        <div style="display: inline;">
+            <button onClick={estimateDifficulty}>Estimate difficulty</button>
+            <Show when={estimate()}>
+                {(report) => <>
+                    &nbsp;Win chance: {(report().win_probability * 100).toFixed(0)}%,
+                    guesses: {report().average_guesses.toFixed(1)},
+                    3BV: {report().average_bbbv.toFixed(0)}
+                </>}
+            </Show>
+        </div>
+        <br />
         <div style="display: inline;">
             Enable questions:&nbsp;
             <button onClick={() => props.setQuestionsEnabled((c: boolean) => !c)}>